@@ -3,7 +3,10 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use sale::{ExecuteMsg, InstantiateMsg, QueryMsg, State, StateResponse, UserStateResponse};
+use sale::{
+    AllUsersResponse, ExecuteMsg, FrozenResponse, InstantiateMsg, MigrateMsg, QueryMsg,
+    RefundsResponse, State, StateResponse, UserStateResponse,
+};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -13,8 +16,12 @@ fn main() {
 
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(State), &out_dir);
     export_schema(&schema_for!(StateResponse), &out_dir);
     export_schema(&schema_for!(UserStateResponse), &out_dir);
+    export_schema(&schema_for!(AllUsersResponse), &out_dir);
+    export_schema(&schema_for!(FrozenResponse), &out_dir);
+    export_schema(&schema_for!(RefundsResponse), &out_dir);
 }
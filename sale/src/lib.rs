@@ -4,13 +4,14 @@ mod testing;
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_binary, Addr, BankMsg, Binary, CanonicalAddr, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env,
-    MessageInfo, Response, StdError, StdResult, Uint128, WasmMsg,
+    from_binary, to_binary, Addr, BankMsg, Binary, CanonicalAddr, Coin, CosmosMsg, Decimal, Deps,
+    DepsMut, Env, MessageInfo, Order, Response, StdError, StdResult, Storage, Uint128, WasmMsg,
 };
-use cw2::set_contract_version;
-use cw20::Cw20ExecuteMsg;
-use cw_storage_plus::{Item, Map};
+use cw2::{get_contract_version, set_contract_version};
+use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg};
+use cw_storage_plus::{Bound, Item, Map};
 use schemars::JsonSchema;
+use semver::Version;
 use serde::{Deserialize, Serialize};
 use sha3::Digest;
 use std::convert::TryInto;
@@ -26,6 +27,30 @@ pub enum ContractError {
     Std(#[from] StdError),
     #[error("Unauthorized")]
     Unauthorized {},
+    #[error("Paused")]
+    Paused {},
+    #[error("MigrationWrongContract")]
+    MigrationWrongContract {},
+    #[error("MigrationDowngrade")]
+    MigrationDowngrade {},
+    #[error("Frozen")]
+    Frozen {},
+    #[error("InvalidPaymentAsset")]
+    InvalidPaymentAsset {},
+    #[error("SaleFailed")]
+    SaleFailed {},
+    #[error("SaleNotFailed")]
+    SaleNotFailed {},
+    #[error("SaleNotEnded")]
+    SaleNotEnded {},
+    #[error("Cancelled")]
+    Cancelled {},
+    #[error("AlreadyFinalized")]
+    AlreadyFinalized {},
+    #[error("AlreadyCollected")]
+    AlreadyCollected {},
+    #[error("AlreadyClaimed")]
+    AlreadyClaimed {},
     #[error("NoZeroAmount")]
     NoZeroAmount,
     #[error("NoOtherDenoms")]
@@ -54,29 +79,58 @@ pub enum ContractError {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub token: String,
+    pub payment: PaymentAsset,
     pub start_time: u64,
     pub end_time: u64,
     pub raising_amount: Uint128,
+    pub soft_cap: Uint128,
     pub offering_amount: Uint128,
     pub vesting_initial: Uint128,
     pub vesting_time: u64,
+    pub vesting_start: u64,
+    pub vesting_cliff: u64,
     pub merkle_root: String,
+    pub fcfs_cap: FcfsCap,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
+    Receive(Cw20ReceiveMsg),
     Configure {
         token: String,
+        payment: PaymentAsset,
         start_time: u64,
         end_time: u64,
         raising_amount: Uint128,
+        soft_cap: Uint128,
         offering_amount: Uint128,
         vesting_initial: Uint128,
         vesting_time: u64,
+        vesting_start: u64,
+        vesting_cliff: u64,
         merkle_root: String,
+        fcfs_cap: FcfsCap,
         finalized: bool,
     },
+    ConfigureRoles {
+        pauser: String,
+        configurer: String,
+        treasury: String,
+    },
+    Pause {
+        paused: bool,
+    },
+    Freeze {
+        user: String,
+    },
+    Unfreeze {
+        user: String,
+    },
+    TransferOwnership {
+        owner: String,
+    },
+    AcceptOwnership {},
     Deposit {
         allocation: Uint128,
         proof: Vec<String>,
@@ -86,6 +140,8 @@ pub enum ExecuteMsg {
         proof: Vec<String>,
     },
     Harvest {},
+    Refund {},
+    Cancel {},
     Collect {},
     CollectTokens {
         amount: Uint128,
@@ -95,27 +151,66 @@ pub enum ExecuteMsg {
     },
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw20HookMsg {
+    Deposit {
+        allocation: Uint128,
+        proof: Vec<String>,
+    },
+    DepositFcfs {
+        allocation: Uint128,
+        proof: Vec<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     State {},
-    UserState { user: String, now: u64 },
+    UserState {
+        user: String,
+        now: u64,
+    },
+    AllUsers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    Frozen {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    Refunds {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct StateResponse {
     pub owner: String,
+    pub pending_owner: Option<String>,
+    pub pauser: String,
+    pub configurer: String,
+    pub treasury: String,
     pub token: String,
+    pub payment: PaymentAsset,
     pub start_time: u64,
     pub end_time: u64,
     pub raising_amount: Uint128,
+    pub soft_cap: Uint128,
     pub offering_amount: Uint128,
     pub merkle_root: String,
+    pub fcfs_cap: FcfsCap,
     pub finalized: bool,
+    pub paused: bool,
     pub total_users: u64,
     pub total_amount: Uint128,
     pub vesting_initial: Uint128,
     pub vesting_time: u64,
+    pub vesting_start: u64,
+    pub vesting_cliff: u64,
+    pub cancelled: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -126,33 +221,108 @@ pub struct UserStateResponse {
     pub claimable: Uint128,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AllUsersResponse {
+    pub users: Vec<UserStateEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FrozenResponse {
+    pub users: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RefundsResponse {
+    pub cancelled: bool,
+    pub refunded: Uint128,
+    pub outstanding: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserStateEntry {
+    pub user: String,
+    pub state: UserStateResponse,
+}
+
 // STATE
 // -----------------------------------------------------
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
     pub owner: CanonicalAddr,
+    pub pending_owner: Option<CanonicalAddr>, // proposed by transfer_ownership
+    pub pauser: CanonicalAddr,
+    pub configurer: CanonicalAddr,
+    pub treasury: CanonicalAddr, // receives collected funds
     pub token: CanonicalAddr,
+    pub payment: PaymentAsset, // deposited, and collected by the treasury
     pub start_time: u64,
     pub end_time: u64,
     pub raising_amount: Uint128,
+    pub soft_cap: Uint128, // refunds if total_amount is below it at end_time, 0 disables
     pub offering_amount: Uint128,
     pub merkle_root: String,
+    pub fcfs_cap: FcfsCap,
     pub finalized: bool,
+    pub paused: bool,
     pub total_users: u64,
     pub total_amount: Uint128,
     pub vesting_initial: Uint128, // vested initially 1e6 = 100%
-    pub vesting_time: u64,        // time past end_time to 100% vested
+    pub vesting_time: u64,        // time past the cliff to 100% vested
+    pub vesting_start: u64,       // TGE, nothing is claimable before it, 0 vests from end_time
+    pub vesting_cliff: u64,       // time past vesting_start before linear vesting
+    pub cancelled: bool,          // set by the owner, depositors get refunded
+    pub collected: bool,          // deposits were sent to the treasury, the sale can't be cancelled
+    pub harvested: bool,          // tokens were sent to a depositor, the sale can't be cancelled
+    pub total_refunded: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentAsset {
+    Native { denom: String },
+    Token { address: Addr },
+}
+
+// Limit on the total deposit_fcfs of each user
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FcfsCap {
+    Flat { amount: Uint128 },
+    Allocation { multiple: Decimal }, // of the user's merkle allocation
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
 pub struct UserState {
     pub amount: Uint128,
     pub claimed: Uint128,
+    #[serde(default)]
+    pub fcfs_amount: Uint128, // part of `amount` deposited through deposit_fcfs
 }
 
 pub const STATE: Item<State> = Item::new("state");
 pub const USERS_STATE: Map<&Addr, UserState> = Map::new("users");
+// Users that can't deposit or harvest
+pub const FROZEN: Map<&Addr, bool> = Map::new("frozen");
+
+// State as stored by 1.0.0
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct StateV1_0 {
+    owner: CanonicalAddr,
+    token: CanonicalAddr,
+    start_time: u64,
+    end_time: u64,
+    raising_amount: Uint128,
+    offering_amount: Uint128,
+    merkle_root: String,
+    finalized: bool,
+    total_users: u64,
+    total_amount: Uint128,
+    vesting_initial: Uint128,
+    vesting_time: u64,
+}
+
+const STATE_V1_0: Item<StateV1_0> = Item::new("state");
 
 // CONTRACT
 // -----------------------------------------------------
@@ -171,17 +341,31 @@ pub fn instantiate(
     let token_addr = deps.api.addr_canonicalize(&msg.token)?;
     let state = State {
         owner: sender_addr.clone(),
+        pending_owner: None,
+        pauser: sender_addr.clone(),
+        configurer: sender_addr.clone(),
+        treasury: sender_addr.clone(),
         token: token_addr.clone(),
+        payment: msg.payment,
         start_time: msg.start_time,
         end_time: msg.end_time,
         raising_amount: msg.raising_amount,
+        soft_cap: msg.soft_cap,
         offering_amount: msg.offering_amount,
         vesting_initial: msg.vesting_initial,
         vesting_time: msg.vesting_time,
+        vesting_start: msg.vesting_start,
+        vesting_cliff: msg.vesting_cliff,
         merkle_root: msg.merkle_root.clone(),
+        fcfs_cap: msg.fcfs_cap,
         finalized: false,
+        paused: false,
         total_users: 0,
         total_amount: Uint128::zero(),
+        cancelled: false,
+        collected: false,
+        harvested: false,
+        total_refunded: Uint128::zero(),
     };
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     STATE.save(deps.storage, &state)?;
@@ -193,9 +377,12 @@ pub fn instantiate(
         ("start_time", msg.start_time.to_string().as_str()),
         ("end_time", msg.end_time.to_string().as_str()),
         ("raising_amount", msg.raising_amount.to_string().as_str()),
+        ("soft_cap", msg.soft_cap.to_string().as_str()),
         ("offering_amount", msg.offering_amount.to_string().as_str()),
         ("vesting_initial", msg.vesting_initial.to_string().as_str()),
         ("vesting_time", msg.vesting_time.to_string().as_str()),
+        ("vesting_start", msg.vesting_start.to_string().as_str()),
+        ("vesting_cliff", msg.vesting_cliff.to_string().as_str()),
         ("merkle_root", msg.merkle_root.to_string().as_str()),
     ]))
 }
@@ -208,38 +395,65 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
+        ExecuteMsg::Receive(msg) => receive_cw20(deps, env, info, msg),
         ExecuteMsg::Configure {
             token,
+            payment,
             start_time,
             end_time,
             raising_amount,
+            soft_cap,
             offering_amount,
             vesting_initial,
             vesting_time,
+            vesting_start,
+            vesting_cliff,
             merkle_root,
+            fcfs_cap,
             finalized,
         } => configure(
             deps,
             env,
             info,
             token,
+            payment,
             start_time,
             end_time,
             raising_amount,
+            soft_cap,
             offering_amount,
             vesting_initial,
             vesting_time,
+            vesting_start,
+            vesting_cliff,
             merkle_root,
+            fcfs_cap,
             finalized,
         ),
-        ExecuteMsg::Deposit { allocation, proof } => deposit(deps, env, info, allocation, proof),
+        ExecuteMsg::ConfigureRoles {
+            pauser,
+            configurer,
+            treasury,
+        } => configure_roles(deps, env, info, pauser, configurer, treasury),
+        ExecuteMsg::Pause { paused } => pause(deps, env, info, paused),
+        ExecuteMsg::Freeze { user } => freeze(deps, env, info, user, true),
+        ExecuteMsg::Unfreeze { user } => freeze(deps, env, info, user, false),
+        ExecuteMsg::TransferOwnership { owner } => transfer_ownership(deps, env, info, owner),
+        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, env, info),
+        ExecuteMsg::Deposit { allocation, proof } => {
+            let amount = native_funds(deps.storage, &info)?;
+            deposit(deps, env, info.sender, amount, allocation, proof)
+        }
         ExecuteMsg::DepositFcfs { allocation, proof } => {
-            deposit_fcfs(deps, env, info, allocation, proof)
+            let amount = native_funds(deps.storage, &info)?;
+            deposit_fcfs(deps, env, info.sender, amount, allocation, proof)
         }
         ExecuteMsg::Harvest {} => harvest(deps, env, info),
+        ExecuteMsg::Refund {} => refund(deps, env, info),
+        ExecuteMsg::Cancel {} => cancel(deps, env, info),
         ExecuteMsg::Collect {} => collect(deps, env, info),
         ExecuteMsg::CollectTokens { amount } => collect_tokens(deps, env, info, amount),
-        ExecuteMsg::Migrate { new_contract } => migrate(deps, env, info, new_contract),
+        ExecuteMsg::Migrate { new_contract } => migrate_funds(deps, env, info, new_contract),
     }
 }
 
@@ -248,31 +462,44 @@ pub fn configure(
     _env: Env,
     info: MessageInfo,
     token: String,
+    payment: PaymentAsset,
     start_time: u64,
     end_time: u64,
     raising_amount: Uint128,
+    soft_cap: Uint128,
     offering_amount: Uint128,
     vesting_initial: Uint128,
     vesting_time: u64,
+    vesting_start: u64,
+    vesting_cliff: u64,
     merkle_root: String,
+    fcfs_cap: FcfsCap,
     finalized: bool,
 ) -> Result<Response, ContractError> {
     let state = STATE.load(deps.storage)?;
     let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
     let token_addr = deps.api.addr_canonicalize(&token)?;
-    if state.owner != sender_addr {
+    if state.configurer != sender_addr {
         return Err(ContractError::Unauthorized {});
     }
+    if payment != state.payment && !state.total_amount.is_zero() {
+        return Err(ContractError::InvalidPaymentAsset {});
+    }
 
     STATE.update(deps.storage, |mut state| -> StdResult<_> {
         state.token = token_addr.clone();
+        state.payment = payment;
         state.start_time = start_time;
         state.end_time = end_time;
         state.raising_amount = raising_amount;
+        state.soft_cap = soft_cap;
         state.offering_amount = offering_amount;
         state.vesting_initial = vesting_initial;
         state.vesting_time = vesting_time;
+        state.vesting_start = vesting_start;
+        state.vesting_cliff = vesting_cliff;
         state.merkle_root = merkle_root.clone();
+        state.fcfs_cap = fcfs_cap;
         state.finalized = finalized;
         Ok(state)
     })?;
@@ -283,24 +510,189 @@ pub fn configure(
         ("start_time", start_time.to_string().as_str()),
         ("end_time", end_time.to_string().as_str()),
         ("raising_amount", raising_amount.to_string().as_str()),
+        ("soft_cap", soft_cap.to_string().as_str()),
         ("offering_amount", offering_amount.to_string().as_str()),
         ("vesting_initial", vesting_initial.to_string().as_str()),
         ("vesting_time", vesting_time.to_string().as_str()),
+        ("vesting_start", vesting_start.to_string().as_str()),
+        ("vesting_cliff", vesting_cliff.to_string().as_str()),
         ("merkle_root", merkle_root.as_str()),
         ("finalized", finalized.to_string().as_str()),
     ]))
 }
 
-pub fn deposit(
+pub fn configure_roles(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    pauser: String,
+    configurer: String,
+    treasury: String,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if state.owner != sender_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let pauser_addr = deps.api.addr_canonicalize(&pauser)?;
+    let configurer_addr = deps.api.addr_canonicalize(&configurer)?;
+    let treasury_addr = deps.api.addr_canonicalize(&treasury)?;
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.pauser = pauser_addr;
+        state.configurer = configurer_addr;
+        state.treasury = treasury_addr;
+        Ok(state)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "configure_roles"),
+        ("pauser", pauser.as_str()),
+        ("configurer", configurer.as_str()),
+        ("treasury", treasury.as_str()),
+    ]))
+}
+
+pub fn pause(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    paused: bool,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if state.pauser != sender_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.paused = paused;
+        Ok(state)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "pause"),
+        ("paused", paused.to_string().as_str()),
+    ]))
+}
+
+pub fn freeze(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    user: String,
+    frozen: bool,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if state.owner != sender_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let user_addr = deps.api.addr_validate(&user)?;
+    if frozen {
+        FROZEN.save(deps.storage, &user_addr, &true)?;
+    } else {
+        FROZEN.remove(deps.storage, &user_addr);
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "freeze"),
+        ("user", user_addr.as_str()),
+        ("frozen", frozen.to_string().as_str()),
+    ]))
+}
+
+pub fn transfer_ownership(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    owner: String,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if state.owner != sender_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let owner_addr = deps.api.addr_canonicalize(&owner)?;
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.pending_owner = Some(owner_addr);
+        Ok(state)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "transfer_ownership"),
+        ("pending_owner", owner.as_str()),
+    ]))
+}
+
+pub fn accept_ownership(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if state.pending_owner != Some(sender_addr.clone()) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.owner = sender_addr;
+        state.pending_owner = None;
+        Ok(state)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "accept_ownership"),
+        ("owner", info.sender.as_str()),
+    ]))
+}
+
+pub fn receive_cw20(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if !matches!(&state.payment, PaymentAsset::Token { address } if address == &info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let cw20_sender = deps.api.addr_validate(&msg.sender)?;
+    match from_binary(&msg.msg) {
+        Ok(Cw20HookMsg::Deposit { allocation, proof }) => {
+            deposit(deps, env, cw20_sender, msg.amount, allocation, proof)
+        }
+        Ok(Cw20HookMsg::DepositFcfs { allocation, proof }) => {
+            deposit_fcfs(deps, env, cw20_sender, msg.amount, allocation, proof)
+        }
+        Err(_) => Err(ContractError::Std(StdError::generic_err(
+            "missing message data",
+        ))),
+    }
+}
+
+pub fn deposit(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    amount: Uint128,
     allocation: Uint128,
     proof: Vec<String>,
 ) -> Result<Response, ContractError> {
-    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
-    let sender = deps.api.addr_humanize(&sender_addr)?;
     let state = STATE.load(deps.storage)?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
+    if FROZEN.has(deps.storage, &sender) {
+        return Err(ContractError::Frozen {});
+    }
+    if state.cancelled {
+        return Err(ContractError::Cancelled {});
+    }
     if env.block.time.seconds() < state.start_time {
         return Err(ContractError::DepositNotStarted {});
     }
@@ -311,18 +703,6 @@ pub fn deposit(
     let user_input = sender.to_string() + "," + &allocation.to_string();
     merkle_verify(state.merkle_root, user_input, proof)?;
 
-    let amount = info
-        .funds
-        .iter()
-        .find(|c| c.denom == "uusd")
-        .map(|c| Uint128::from(c.amount))
-        .unwrap_or_else(Uint128::zero);
-    if amount.is_zero() {
-        return Err(ContractError::NoZeroAmount {});
-    }
-    if info.funds.len() > 1 {
-        return Err(ContractError::NoOtherDenoms {});
-    }
     if state.total_amount.saturating_add(amount) > state.raising_amount {
         return Err(ContractError::OverRaisingAmount {});
     }
@@ -360,50 +740,48 @@ pub fn deposit(
 pub fn deposit_fcfs(
     deps: DepsMut,
     env: Env,
-    info: MessageInfo,
+    sender: Addr,
+    amount: Uint128,
     allocation: Uint128,
     proof: Vec<String>,
 ) -> Result<Response, ContractError> {
-    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
-    let sender = deps.api.addr_humanize(&sender_addr)?;
     let state = STATE.load(deps.storage)?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
+    if FROZEN.has(deps.storage, &sender) {
+        return Err(ContractError::Frozen {});
+    }
+    if state.cancelled {
+        return Err(ContractError::Cancelled {});
+    }
     if env.block.time.seconds() <= state.end_time {
         return Err(ContractError::DepositFcfsNotStarted {});
     }
+    if sale_failed(&state, env.block.time.seconds()) {
+        return Err(ContractError::SaleFailed {});
+    }
 
     let user_input = sender.to_string() + "," + &allocation.to_string();
     merkle_verify(state.merkle_root, user_input, proof)?;
 
-    let amount = info
-        .funds
-        .iter()
-        .find(|c| c.denom == "uusd")
-        .map(|c| Uint128::from(c.amount))
-        .unwrap_or_else(Uint128::zero);
-    if amount.is_zero() {
-        return Err(ContractError::NoZeroAmount {});
-    }
-    if info.funds.len() > 1 {
-        return Err(ContractError::NoOtherDenoms {});
-    }
-    if amount > Uint128::from(250 * ONE) {
+    let maybe_user_state = USERS_STATE.may_load(deps.storage, &sender)?;
+    let is_new_user = maybe_user_state.is_none();
+    let mut user_state = maybe_user_state.unwrap_or_default();
+    let cap = match state.fcfs_cap {
+        FcfsCap::Flat { amount } => amount,
+        FcfsCap::Allocation { multiple } => allocation * multiple,
+    };
+    if user_state.fcfs_amount + amount > cap {
         return Err(ContractError::OverFcfsWalletCap {});
     }
     if state.total_amount.saturating_add(amount) > state.raising_amount {
         return Err(ContractError::OverRaisingAmount {});
     }
 
-    let mut is_new_user = false;
-    USERS_STATE.update(
-        deps.storage,
-        &sender,
-        |maybe_user_state| -> Result<_, ContractError> {
-            is_new_user = maybe_user_state.is_none();
-            let mut user_state = maybe_user_state.unwrap_or_default();
-            user_state.amount += amount;
-            Ok(user_state)
-        },
-    )?;
+    user_state.amount += amount;
+    user_state.fcfs_amount += amount;
+    USERS_STATE.save(deps.storage, &sender, &user_state)?;
 
     STATE.update(deps.storage, |mut state| -> StdResult<_> {
         state.total_amount += amount;
@@ -424,9 +802,22 @@ pub fn harvest(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, C
     let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
     let sender = deps.api.addr_humanize(&sender_addr)?;
     let state = STATE.load(deps.storage)?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
+    if FROZEN.has(deps.storage, &sender) {
+        return Err(ContractError::Frozen {});
+    }
     if !state.finalized {
         return Err(ContractError::NotFinalized {});
     }
+    if sale_failed(&state, env.block.time.seconds()) {
+        return Err(ContractError::SaleFailed {});
+    }
+    // The sale can still fail and be refunded until it ends
+    if !state.soft_cap.is_zero() && env.block.time.seconds() <= state.end_time {
+        return Err(ContractError::SaleNotEnded {});
+    }
 
     let mut amount = Uint128::zero();
     USERS_STATE.update(
@@ -443,6 +834,12 @@ pub fn harvest(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, C
             Ok(user_state)
         },
     )?;
+    if !state.harvested {
+        STATE.update(deps.storage, |mut state| -> StdResult<_> {
+            state.harvested = true;
+            Ok(state)
+        })?;
+    }
 
     Ok(Response::new()
         .add_messages(vec![CosmosMsg::Wasm(WasmMsg::Execute {
@@ -460,29 +857,107 @@ pub fn harvest(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, C
         ]))
 }
 
-pub fn collect(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+pub fn refund(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
+    if !sale_failed(&state, env.block.time.seconds()) {
+        return Err(ContractError::SaleNotFailed {});
+    }
+
+    let mut user_state = USERS_STATE
+        .may_load(deps.storage, &info.sender)?
+        .unwrap_or_default();
+    let amount = user_state.amount;
+    if amount.is_zero() {
+        return Err(ContractError::NoZeroAmount {});
+    }
+    if !user_state.claimed.is_zero() {
+        return Err(ContractError::AlreadyClaimed {});
+    }
+    user_state.amount = Uint128::zero();
+    USERS_STATE.save(deps.storage, &info.sender, &user_state)?;
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.total_amount -= amount;
+        state.total_refunded += amount;
+        Ok(state)
+    })?;
+
+    let (message, amount) = transfer_payment(deps.as_ref(), &state, &info.sender, amount)?;
+    Ok(Response::new().add_message(message).add_attributes(vec![
+        ("action", "refund"),
+        ("user", info.sender.as_str()),
+        ("amount", amount.to_string().as_str()),
+    ]))
+}
+
+pub fn cancel(deps: DepsMut, _env: Env, info: MessageInfo) -> Result<Response, ContractError> {
     let state = STATE.load(deps.storage)?;
     let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
-    let sender = deps.api.addr_humanize(&sender_addr)?;
     if state.owner != sender_addr {
         return Err(ContractError::Unauthorized {});
     }
+    if state.finalized {
+        return Err(ContractError::AlreadyFinalized {});
+    }
+    if state.collected {
+        return Err(ContractError::AlreadyCollected {});
+    }
+    // Unlike finalized, this can't be undone by configure
+    if state.harvested {
+        return Err(ContractError::AlreadyClaimed {});
+    }
 
-    let balance = deps
-        .querier
-        .query_balance(env.contract.address, "uusd")
-        .unwrap();
-    let balance_after_tax = deduct_tax(deps, balance)?;
-    Ok(Response::new()
-        .add_message(CosmosMsg::Bank(BankMsg::Send {
-            to_address: sender.to_string(),
-            amount: vec![balance_after_tax.clone()],
-        }))
-        .add_attributes(vec![
-            ("action", "collect"),
-            ("user", sender.to_string().as_str()),
-            ("amount", balance_after_tax.amount.to_string().as_str()),
-        ]))
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.cancelled = true;
+        Ok(state)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![("action", "cancel")]))
+}
+
+pub fn collect(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if state.owner != sender_addr && state.treasury != sender_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+    if sale_failed(&state, env.block.time.seconds()) {
+        return Err(ContractError::SaleFailed {});
+    }
+    // Deposits stay refundable until the soft cap is known to be reached
+    if !state.soft_cap.is_zero() && env.block.time.seconds() <= state.end_time {
+        return Err(ContractError::SaleNotEnded {});
+    }
+    let treasury = deps.api.addr_humanize(&state.treasury)?;
+
+    let balance = match &state.payment {
+        PaymentAsset::Native { denom } => {
+            deps.querier
+                .query_balance(env.contract.address, denom)?
+                .amount
+        }
+        PaymentAsset::Token { address } => {
+            let res: BalanceResponse = deps.querier.query_wasm_smart(
+                address,
+                &Cw20QueryMsg::Balance {
+                    address: env.contract.address.to_string(),
+                },
+            )?;
+            res.balance
+        }
+    };
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.collected = true;
+        Ok(state)
+    })?;
+    let (message, amount) = transfer_payment(deps.as_ref(), &state, &treasury, balance)?;
+    Ok(Response::new().add_message(message).add_attributes(vec![
+        ("action", "collect"),
+        ("user", treasury.to_string().as_str()),
+        ("amount", amount.to_string().as_str()),
+    ]))
 }
 
 pub fn collect_tokens(
@@ -493,28 +968,28 @@ pub fn collect_tokens(
 ) -> Result<Response, ContractError> {
     let state = STATE.load(deps.storage)?;
     let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
-    let sender = deps.api.addr_humanize(&sender_addr)?;
-    if state.owner != sender_addr {
+    if state.owner != sender_addr && state.treasury != sender_addr {
         return Err(ContractError::Unauthorized {});
     }
+    let treasury = deps.api.addr_humanize(&state.treasury)?;
 
     Ok(Response::new()
         .add_messages(vec![CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: deps.api.addr_humanize(&state.token)?.to_string(),
             msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                recipient: sender.to_string(),
+                recipient: treasury.to_string(),
                 amount,
             })?,
             funds: vec![],
         })])
         .add_attributes(vec![
             ("action", "collect_tokens"),
-            ("user", sender.to_string().as_str()),
+            ("user", treasury.to_string().as_str()),
             ("amount", amount.to_string().as_str()),
         ]))
 }
 
-pub fn migrate(
+pub fn migrate_funds(
     _deps: DepsMut,
     _env: Env,
     _info: MessageInfo,
@@ -524,10 +999,78 @@ pub fn migrate(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let previous = get_contract_version(deps.storage)?;
+    if previous.contract != CONTRACT_NAME {
+        return Err(ContractError::MigrationWrongContract {});
+    }
+    let previous_version = parse_version(&previous.version)?;
+    if previous_version > parse_version(CONTRACT_VERSION)? {
+        return Err(ContractError::MigrationDowngrade {});
+    }
+
+    if previous_version < Version::new(1, 1, 0) {
+        migrate_state_v1_0(deps.storage)?;
+    }
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "migrate"),
+        ("from_version", previous.version.as_str()),
+        ("to_version", CONTRACT_VERSION),
+    ]))
+}
+
+// Roles default to the owner
+fn migrate_state_v1_0(storage: &mut dyn Storage) -> StdResult<()> {
+    let legacy = STATE_V1_0.load(storage)?;
+    let state = State {
+        owner: legacy.owner.clone(),
+        pending_owner: None,
+        pauser: legacy.owner.clone(),
+        configurer: legacy.owner.clone(),
+        treasury: legacy.owner,
+        token: legacy.token,
+        payment: PaymentAsset::Native {
+            denom: "uusd".to_string(),
+        },
+        start_time: legacy.start_time,
+        end_time: legacy.end_time,
+        raising_amount: legacy.raising_amount,
+        soft_cap: Uint128::zero(),
+        offering_amount: legacy.offering_amount,
+        merkle_root: legacy.merkle_root,
+        fcfs_cap: FcfsCap::Flat {
+            amount: Uint128::from(250 * ONE),
+        },
+        finalized: legacy.finalized,
+        paused: false,
+        total_users: legacy.total_users,
+        total_amount: legacy.total_amount,
+        vesting_initial: legacy.vesting_initial,
+        vesting_time: legacy.vesting_time,
+        vesting_start: 0,
+        vesting_cliff: 0,
+        cancelled: false,
+        collected: true, // unknown for 1.0.0 sales
+        harvested: legacy.finalized,
+        total_refunded: Uint128::zero(),
+    };
+    STATE.save(storage, &state)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::State {} => to_binary(&query_state(deps)?),
         QueryMsg::UserState { user, now } => to_binary(&query_user_state(deps, user, now)?),
+        QueryMsg::AllUsers { start_after, limit } => {
+            to_binary(&query_all_users(deps, env, start_after, limit)?)
+        }
+        QueryMsg::Frozen { start_after, limit } => {
+            to_binary(&query_frozen(deps, start_after, limit)?)
+        }
+        QueryMsg::Refunds {} => to_binary(&query_refunds(deps)?),
     }
 }
 
@@ -535,17 +1078,40 @@ fn query_state(deps: Deps) -> StdResult<StateResponse> {
     let state = STATE.load(deps.storage)?;
     Ok(StateResponse {
         owner: deps.api.addr_humanize(&state.owner)?.to_string(),
+        pending_owner: state
+            .pending_owner
+            .map(|a| deps.api.addr_humanize(&a).map(|a| a.to_string()))
+            .transpose()?,
+        pauser: deps.api.addr_humanize(&state.pauser)?.to_string(),
+        configurer: deps.api.addr_humanize(&state.configurer)?.to_string(),
+        treasury: deps.api.addr_humanize(&state.treasury)?.to_string(),
         token: deps.api.addr_humanize(&state.token)?.to_string(),
+        payment: state.payment,
         start_time: state.start_time,
         end_time: state.end_time,
         raising_amount: state.raising_amount,
+        soft_cap: state.soft_cap,
         offering_amount: state.offering_amount,
         vesting_initial: state.vesting_initial,
         vesting_time: state.vesting_time,
+        vesting_start: state.vesting_start,
+        vesting_cliff: state.vesting_cliff,
         merkle_root: state.merkle_root,
+        fcfs_cap: state.fcfs_cap,
         finalized: state.finalized,
+        paused: state.paused,
         total_users: state.total_users,
         total_amount: state.total_amount,
+        cancelled: state.cancelled,
+    })
+}
+
+fn query_refunds(deps: Deps) -> StdResult<RefundsResponse> {
+    let state = STATE.load(deps.storage)?;
+    Ok(RefundsResponse {
+        cancelled: state.cancelled,
+        refunded: state.total_refunded,
+        outstanding: state.total_amount,
     })
 }
 
@@ -556,19 +1122,63 @@ fn query_user_state(deps: Deps, user: String, now: u64) -> StdResult<UserStateRe
     let user_state = USERS_STATE
         .may_load(deps.storage, &user)?
         .unwrap_or_default();
-    let (owed, claimable) = user_vesting(&state, &user_state, now);
-    Ok(UserStateResponse {
+    Ok(user_state_response(&state, &user_state, now))
+}
+
+fn query_all_users(
+    deps: Deps,
+    env: Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<AllUsersResponse> {
+    let state = STATE.load(deps.storage)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let users = USERS_STATE
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, user_state) = item?;
+            Ok(UserStateEntry {
+                user: String::from_utf8(key)?,
+                state: user_state_response(&state, &user_state, env.block.time.seconds()),
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(AllUsersResponse { users })
+}
+
+fn query_frozen(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<FrozenResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let users = FROZEN
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(String::from_utf8)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(FrozenResponse { users })
+}
+
+fn user_state_response(state: &State, user_state: &UserState, now: u64) -> UserStateResponse {
+    let (owed, claimable) = user_vesting(state, user_state, now);
+    UserStateResponse {
         amount: user_state.amount,
         claimed: user_state.claimed,
         owed: owed,
         claimable: claimable,
-    })
+    }
 }
 
 // HELPERS
 // -----------------------------------------------------
 
 const ONE: u128 = 1000000_u128;
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
 
 fn one() -> Uint128 {
     Uint128::from(1000000_u128)
@@ -578,14 +1188,94 @@ fn user_vesting(state: &State, user_state: &UserState, now: u64) -> (Uint128, Ui
     let owed = user_state
         .amount
         .multiply_ratio(state.offering_amount, state.raising_amount);
-    let vesting_progress = now.saturating_sub(state.end_time).min(state.vesting_time);
-    let claimable = owed.multiply_ratio(state.vesting_initial, one())
-        + owed
-            .multiply_ratio(one().saturating_sub(state.vesting_initial), one())
-            .multiply_ratio(vesting_progress, state.vesting_time);
+    if now < state.vesting_start {
+        return (owed, Uint128::zero());
+    }
+    let start = if state.vesting_start == 0 {
+        state.end_time
+    } else {
+        state.vesting_start
+    };
+    let vesting_amount = owed.multiply_ratio(one().saturating_sub(state.vesting_initial), one());
+    let vested = if state.vesting_time == 0 {
+        // Without linear vesting everything unlocks at the cliff
+        if now >= start + state.vesting_cliff {
+            vesting_amount
+        } else {
+            Uint128::zero()
+        }
+    } else {
+        let vesting_progress = now
+            .saturating_sub(start + state.vesting_cliff)
+            .min(state.vesting_time);
+        vesting_amount.multiply_ratio(vesting_progress, state.vesting_time)
+    };
+    let claimable = owed.multiply_ratio(state.vesting_initial, one()) + vested;
     return (owed, claimable);
 }
 
+// Cancelled, or ended below the soft cap: deposits are refunded
+fn sale_failed(state: &State, now: u64) -> bool {
+    state.cancelled || (now > state.end_time && state.total_amount < state.soft_cap)
+}
+
+fn native_funds(storage: &dyn Storage, info: &MessageInfo) -> Result<Uint128, ContractError> {
+    let denom = match STATE.load(storage)?.payment {
+        PaymentAsset::Native { denom } => denom,
+        PaymentAsset::Token { .. } => return Err(ContractError::InvalidPaymentAsset {}),
+    };
+    let amount = info
+        .funds
+        .iter()
+        .find(|c| c.denom == denom)
+        .map(|c| c.amount)
+        .unwrap_or_else(Uint128::zero);
+    if amount.is_zero() {
+        return Err(ContractError::NoZeroAmount {});
+    }
+    if info.funds.len() > 1 {
+        return Err(ContractError::NoOtherDenoms {});
+    }
+    Ok(amount)
+}
+
+// Returns the message and the amount received, native payments pay the tax out of `amount`
+fn transfer_payment(
+    deps: Deps,
+    state: &State,
+    recipient: &Addr,
+    amount: Uint128,
+) -> StdResult<(CosmosMsg, Uint128)> {
+    Ok(match &state.payment {
+        PaymentAsset::Native { denom } => {
+            let coin = deduct_tax(deps, Coin::new(amount.u128(), denom))?;
+            let amount = coin.amount;
+            let message = CosmosMsg::Bank(BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: vec![coin],
+            });
+            (message, amount)
+        }
+        PaymentAsset::Token { address } => {
+            let message = CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: address.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: recipient.to_string(),
+                    amount,
+                })?,
+                funds: vec![],
+            });
+            (message, amount)
+        }
+    })
+}
+
+fn parse_version(version: &str) -> StdResult<Version> {
+    version
+        .parse()
+        .map_err(|_| StdError::generic_err(format!("Invalid version: {}", version)))
+}
+
 fn merkle_verify(
     merkle_root: String,
     user_input: String,
@@ -637,7 +1327,7 @@ fn bytes_cmp(a: [u8; 32], b: [u8; 32]) -> std::cmp::Ordering {
 
 static DECIMAL_FRACTION: Uint128 = Uint128::new(1_000_000_000_000_000_000u128);
 
-pub fn compute_tax(deps: DepsMut, coin: &Coin) -> StdResult<Uint128> {
+pub fn compute_tax(deps: Deps, coin: &Coin) -> StdResult<Uint128> {
     let terra_querier = TerraQuerier::new(&deps.querier);
     let tax_rate: Decimal = (terra_querier.query_tax_rate()?).rate;
     let tax_cap: Uint128 = (terra_querier.query_tax_cap(coin.denom.to_string())?).cap;
@@ -650,7 +1340,7 @@ pub fn compute_tax(deps: DepsMut, coin: &Coin) -> StdResult<Uint128> {
     ))
 }
 
-pub fn deduct_tax(deps: DepsMut, coin: Coin) -> StdResult<Coin> {
+pub fn deduct_tax(deps: Deps, coin: Coin) -> StdResult<Coin> {
     let tax_amount = compute_tax(deps, &coin)?;
     Ok(Coin {
         denom: coin.denom,
@@ -21,6 +21,12 @@ fn test_merkle_proof() -> Vec<String> {
     .into()
 }
 
+fn uusd() -> PaymentAsset {
+    PaymentAsset::Native {
+        denom: "uusd".to_string(),
+    }
+}
+
 fn test_setup(deposit: bool) -> OwnedDeps<MockStorage, MockApi, CustomMockQuerier> {
     let mut deps = mock_dependencies(&[Coin::new(80 * ONE, "uusd")]);
     deps.querier.infos.insert(
@@ -39,13 +45,20 @@ fn test_setup(deposit: bool) -> OwnedDeps<MockStorage, MockApi, CustomMockQuerie
         .insert("token0000".to_string(), token_balances);
     let msg = InstantiateMsg {
         token: "token0000".to_string(),
+        payment: uusd(),
         start_time: 10,
         end_time: 100,
         raising_amount: Uint128::from(100 * ONE),
+        soft_cap: Uint128::zero(),
         offering_amount: Uint128::from(500 * ONE),
         vesting_initial: Uint128::from(100000_u128),
         vesting_time: 200,
+        vesting_start: 0,
+        vesting_cliff: 0,
         merkle_root: MERKLE_ROOT.to_string(),
+        fcfs_cap: FcfsCap::Flat {
+            amount: Uint128::from(250 * ONE),
+        },
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -62,37 +75,54 @@ fn test_setup(deposit: bool) -> OwnedDeps<MockStorage, MockApi, CustomMockQuerie
             execute(deps.as_mut(), env, info.clone(), msg).unwrap();
         }
         {
-            let msg = ExecuteMsg::Configure {
-                token: "token0000".to_string(),
-                start_time: 10,
-                end_time: 100,
-                raising_amount: Uint128::from(100 * ONE),
-                offering_amount: Uint128::from(500 * ONE),
-                vesting_initial: Uint128::from(100000_u128),
-                vesting_time: 200,
-                merkle_root: MERKLE_ROOT.to_string(),
-                finalized: true,
-            };
             let info = mock_info("addr0000", &[]);
-            execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+            execute(deps.as_mut(), mock_env(), info.clone(), configure_msg(true)).unwrap();
         }
     }
 
     deps
 }
 
+fn configure_msg(finalized: bool) -> ExecuteMsg {
+    ExecuteMsg::Configure {
+        token: "token0000".to_string(),
+        payment: uusd(),
+        start_time: 10,
+        end_time: 100,
+        raising_amount: Uint128::from(100 * ONE),
+        soft_cap: Uint128::zero(),
+        offering_amount: Uint128::from(500 * ONE),
+        vesting_initial: Uint128::from(100000_u128),
+        vesting_time: 200,
+        vesting_start: 0,
+        vesting_cliff: 0,
+        merkle_root: MERKLE_ROOT.to_string(),
+        fcfs_cap: FcfsCap::Flat {
+            amount: Uint128::from(250 * ONE),
+        },
+        finalized,
+    }
+}
+
 #[test]
 fn test_instantiate() {
     let mut deps = mock_dependencies(&[]);
     let msg = InstantiateMsg {
         token: "token0000".to_string(),
+        payment: uusd(),
         start_time: 0,
         end_time: 100,
         raising_amount: Uint128::from(100_u128),
+        soft_cap: Uint128::zero(),
         offering_amount: Uint128::from(500_u128),
         vesting_initial: Uint128::from(100000_u128),
         vesting_time: 200,
+        vesting_start: 0,
+        vesting_cliff: 0,
         merkle_root: MERKLE_ROOT.to_string(),
+        fcfs_cap: FcfsCap::Flat {
+            amount: Uint128::from(250 * ONE),
+        },
     };
     let info = mock_info("addr0000", &[]);
     let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -116,13 +146,20 @@ fn test_configure_error_not_owner() {
     let mut deps = test_setup(true);
     let msg = ExecuteMsg::Configure {
         token: "token0000".to_string(),
+        payment: uusd(),
         start_time: 10,
         end_time: 100,
         raising_amount: Uint128::from(100 * ONE),
+        soft_cap: Uint128::zero(),
         offering_amount: Uint128::from(500 * ONE),
         vesting_initial: Uint128::from(100000_u128),
         vesting_time: 200,
+        vesting_start: 0,
+        vesting_cliff: 0,
         merkle_root: MERKLE_ROOT.to_string(),
+        fcfs_cap: FcfsCap::Flat {
+            amount: Uint128::from(250 * ONE),
+        },
         finalized: false,
     };
     let info = mock_info("addr0001", &[]);
@@ -288,6 +325,50 @@ fn test_deposit() {
     }
 }
 
+#[test]
+fn test_all_users() {
+    let mut deps = test_setup(false);
+    let msg = ExecuteMsg::Deposit {
+        allocation: Uint128::from(ALLOCATION),
+        proof: test_merkle_proof(),
+    };
+    let info = mock_info("addr0001", &[Coin::new(50 * ONE, "uusd")]);
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(40);
+    execute(deps.as_mut(), env, info.clone(), msg).unwrap();
+    USERS_STATE
+        .save(
+            deps.as_mut().storage,
+            &Addr::unchecked("addr0002"),
+            &UserState {
+                amount: Uint128::from(10 * ONE),
+                claimed: Uint128::zero(),
+                fcfs_amount: Uint128::zero(),
+            },
+        )
+        .unwrap();
+
+    let msg = QueryMsg::AllUsers {
+        start_after: None,
+        limit: Some(1),
+    };
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let value: AllUsersResponse = from_binary(&res).unwrap();
+    assert_eq!(1, value.users.len());
+    assert_eq!("addr0001", value.users[0].user);
+    assert_eq!("50000000", value.users[0].state.amount.to_string());
+
+    let msg = QueryMsg::AllUsers {
+        start_after: Some("addr0001".to_string()),
+        limit: None,
+    };
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let value: AllUsersResponse = from_binary(&res).unwrap();
+    assert_eq!(1, value.users.len());
+    assert_eq!("addr0002", value.users[0].user);
+    assert_eq!("10000000", value.users[0].state.amount.to_string());
+}
+
 #[test]
 fn test_deposit_fcfs_error_not_started() {
     let mut deps = test_setup(false);
@@ -316,6 +397,58 @@ fn test_deposit_fcfs_error_over_wallet_cap() {
     assert_eq!(ContractError::OverFcfsWalletCap {}, err);
 }
 
+#[test]
+fn test_deposit_fcfs_cap() {
+    let mut deps = test_setup(false);
+    let configure = |fcfs_cap: FcfsCap| ExecuteMsg::Configure {
+        token: "token0000".to_string(),
+        payment: uusd(),
+        start_time: 10,
+        end_time: 100,
+        raising_amount: Uint128::from(100 * ONE),
+        soft_cap: Uint128::zero(),
+        offering_amount: Uint128::from(500 * ONE),
+        vesting_initial: Uint128::from(100000_u128),
+        vesting_time: 200,
+        vesting_start: 0,
+        vesting_cliff: 0,
+        merkle_root: MERKLE_ROOT.to_string(),
+        fcfs_cap,
+        finalized: false,
+    };
+    let msg = configure(FcfsCap::Flat {
+        amount: Uint128::from(50 * ONE),
+    });
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::DepositFcfs {
+        allocation: Uint128::from(ALLOCATION),
+        proof: test_merkle_proof(),
+    };
+    let info = mock_info("addr0001", &[Coin::new(30 * ONE, "uusd")]);
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(101);
+    execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    // the cap applies to the total, not to each deposit
+    let err = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap_err();
+    assert_eq!(ContractError::OverFcfsWalletCap {}, err);
+
+    let msg_configure = configure(FcfsCap::Allocation {
+        multiple: Decimal::percent(100),
+    });
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        msg_configure,
+    )
+    .unwrap();
+    execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+    assert_eq!(ContractError::OverFcfsWalletCap {}, err);
+}
+
 #[test]
 fn test_deposit_fcfs_error_invalid_merkle_proof() {
     let mut deps = test_setup(false);
@@ -377,13 +510,20 @@ fn test_harvest_error_not_finalized() {
     {
         let msg = ExecuteMsg::Configure {
             token: "token0000".to_string(),
+            payment: uusd(),
             start_time: 10,
             end_time: 100,
             raising_amount: Uint128::from(100 * ONE),
+            soft_cap: Uint128::zero(),
             offering_amount: Uint128::from(500 * ONE),
             vesting_initial: Uint128::from(100000_u128),
             vesting_time: 200,
+            vesting_start: 0,
+            vesting_cliff: 0,
             merkle_root: MERKLE_ROOT.to_string(),
+            fcfs_cap: FcfsCap::Flat {
+                amount: Uint128::from(250 * ONE),
+            },
             finalized: false,
         };
         let info = mock_info("addr0000", &[]);
@@ -440,6 +580,503 @@ fn test_harvest() {
     );
 }
 
+#[test]
+fn test_vesting_cliff() {
+    let mut deps = test_setup(true);
+    let msg = ExecuteMsg::Configure {
+        token: "token0000".to_string(),
+        payment: uusd(),
+        start_time: 10,
+        end_time: 100,
+        raising_amount: Uint128::from(100 * ONE),
+        soft_cap: Uint128::zero(),
+        offering_amount: Uint128::from(500 * ONE),
+        vesting_initial: Uint128::from(100000_u128),
+        vesting_time: 200,
+        vesting_start: 300,
+        vesting_cliff: 100,
+        merkle_root: MERKLE_ROOT.to_string(),
+        fcfs_cap: FcfsCap::Flat {
+            amount: Uint128::from(250 * ONE),
+        },
+        finalized: true,
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let claimable = |now: u64| {
+        let msg = QueryMsg::UserState {
+            user: "addr0001".to_string(),
+            now,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        from_binary::<UserStateResponse>(&res).unwrap().claimable
+    };
+    assert_eq!(claimable(299), Uint128::zero());
+    assert_eq!(claimable(300), Uint128::from(25 * ONE));
+    assert_eq!(claimable(400), Uint128::from(25 * ONE));
+    assert_eq!(claimable(500), Uint128::from(137500000_u128));
+    assert_eq!(claimable(600), Uint128::from(250 * ONE));
+}
+
+#[test]
+fn test_vesting_cliff_unlock() {
+    let mut deps = test_setup(true);
+    let msg = ExecuteMsg::Configure {
+        token: "token0000".to_string(),
+        payment: uusd(),
+        start_time: 10,
+        end_time: 100,
+        raising_amount: Uint128::from(100 * ONE),
+        soft_cap: Uint128::zero(),
+        offering_amount: Uint128::from(500 * ONE),
+        vesting_initial: Uint128::from(100000_u128),
+        vesting_time: 0,
+        vesting_start: 300,
+        vesting_cliff: 100,
+        merkle_root: MERKLE_ROOT.to_string(),
+        fcfs_cap: FcfsCap::Flat {
+            amount: Uint128::from(250 * ONE),
+        },
+        finalized: true,
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let claimable = |now: u64| {
+        let msg = QueryMsg::UserState {
+            user: "addr0001".to_string(),
+            now,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        from_binary::<UserStateResponse>(&res).unwrap().claimable
+    };
+    assert_eq!(claimable(299), Uint128::zero());
+    assert_eq!(claimable(300), Uint128::from(25 * ONE));
+    assert_eq!(claimable(399), Uint128::from(25 * ONE));
+    assert_eq!(claimable(400), Uint128::from(250 * ONE));
+}
+
+#[test]
+fn test_freeze() {
+    let mut deps = test_setup(false);
+    let msg = ExecuteMsg::Freeze {
+        user: "addr0001".to_string(),
+    };
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::Deposit {
+        allocation: Uint128::from(ALLOCATION),
+        proof: test_merkle_proof(),
+    };
+    let info = mock_info("addr0001", &[Coin::new(50 * ONE, "uusd")]);
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(40);
+    let err = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Frozen {}, err);
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Frozen {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let value: FrozenResponse = from_binary(&res).unwrap();
+    assert_eq!(vec!["addr0001".to_string()], value.users);
+
+    let unfreeze = ExecuteMsg::Unfreeze {
+        user: "addr0001".to_string(),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        unfreeze,
+    )
+    .unwrap();
+    execute(deps.as_mut(), env, info, msg).unwrap();
+}
+
+#[test]
+fn test_transfer_ownership() {
+    let mut deps = test_setup(false);
+    let msg = ExecuteMsg::TransferOwnership {
+        owner: "addr0007".to_string(),
+    };
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::AcceptOwnership {};
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let info = mock_info("addr0007", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let value: StateResponse = from_binary(&res).unwrap();
+    assert_eq!("addr0007", value.owner);
+    assert_eq!(None, value.pending_owner);
+}
+
+#[test]
+fn test_configure_roles() {
+    let mut deps = test_setup(true);
+    let msg = ExecuteMsg::ConfigureRoles {
+        pauser: "pauser0000".to_string(),
+        configurer: "configurer0000".to_string(),
+        treasury: "treasury0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::Pause { paused: true };
+    let info = mock_info("addr0000", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let info = mock_info("pauser0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::Harvest {};
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(ContractError::Paused {}, err);
+
+    // Funds go to the treasury, the pauser can't collect
+    let msg = ExecuteMsg::Collect {};
+    let info = mock_info("pauser0000", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "treasury0000".to_string(),
+            amount: vec![Coin::new(79207920_u128, "uusd")],
+        }))],
+    );
+}
+
+#[test]
+fn test_migrate_version() {
+    let mut deps = mock_dependencies(&[]);
+    cw2::set_contract_version(deps.as_mut().storage, "sale", "1.0.0").unwrap();
+    let owner = deps.as_ref().api.addr_canonicalize("addr0000").unwrap();
+    let token = deps.as_ref().api.addr_canonicalize("token0000").unwrap();
+    STATE_V1_0
+        .save(
+            deps.as_mut().storage,
+            &StateV1_0 {
+                owner,
+                token,
+                start_time: 10,
+                end_time: 100,
+                raising_amount: Uint128::from(100 * ONE),
+                offering_amount: Uint128::from(500 * ONE),
+                merkle_root: MERKLE_ROOT.to_string(),
+                finalized: false,
+                total_users: 1,
+                total_amount: Uint128::from(50 * ONE),
+                vesting_initial: Uint128::from(100000_u128),
+                vesting_time: 200,
+            },
+        )
+        .unwrap();
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "migrate"),
+            attr("from_version", "1.0.0"),
+            attr("to_version", CONTRACT_VERSION),
+        ]
+    );
+    let version = cw2::get_contract_version(deps.as_ref().storage).unwrap();
+    assert_eq!(CONTRACT_NAME, version.contract);
+    assert_eq!(CONTRACT_VERSION, version.version);
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let value: StateResponse = from_binary(&res).unwrap();
+    assert_eq!("addr0000", value.owner);
+    assert_eq!("addr0000", value.treasury);
+    assert!(!value.paused);
+    assert_eq!("50000000", value.total_amount.to_string());
+
+    cw2::set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "9.0.0").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert_eq!(ContractError::MigrationDowngrade {}, err);
+
+    cw2::set_contract_version(deps.as_mut().storage, "ts-tiers", "1.0.0").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert_eq!(ContractError::MigrationWrongContract {}, err);
+}
+
+#[test]
+fn test_deposit_cw20() {
+    let mut deps = mock_dependencies(&[]);
+    let mut token_balances = HashMap::new();
+    token_balances.insert(MOCK_CONTRACT_ADDR.to_string(), Uint128::from(50 * ONE));
+    deps.querier
+        .balances
+        .insert("token0001".to_string(), token_balances);
+    let msg = InstantiateMsg {
+        token: "token0000".to_string(),
+        payment: PaymentAsset::Token {
+            address: Addr::unchecked("token0001"),
+        },
+        start_time: 10,
+        end_time: 100,
+        raising_amount: Uint128::from(100 * ONE),
+        soft_cap: Uint128::zero(),
+        offering_amount: Uint128::from(500 * ONE),
+        vesting_initial: Uint128::from(100000_u128),
+        vesting_time: 200,
+        vesting_start: 0,
+        vesting_cliff: 0,
+        merkle_root: MERKLE_ROOT.to_string(),
+        fcfs_cap: FcfsCap::Flat {
+            amount: Uint128::from(250 * ONE),
+        },
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(40);
+
+    let msg = ExecuteMsg::Deposit {
+        allocation: Uint128::from(ALLOCATION),
+        proof: test_merkle_proof(),
+    };
+    let info = mock_info("addr0001", &[Coin::new(50 * ONE, "uusd")]);
+    let err = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+    assert_eq!(ContractError::InvalidPaymentAsset {}, err);
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(50 * ONE),
+        msg: to_binary(&Cw20HookMsg::Deposit {
+            allocation: Uint128::from(ALLOCATION),
+            proof: test_merkle_proof(),
+        })
+        .unwrap(),
+    });
+    let info = mock_info("token0000", &[]);
+    let err = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let info = mock_info("token0001", &[]);
+    execute(deps.as_mut(), env, info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let value: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(Uint128::from(50 * ONE), value.total_amount);
+
+    let msg = ExecuteMsg::Collect {};
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "token0001".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0000".to_string(),
+                amount: Uint128::from(50 * ONE),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))],
+    );
+}
+
+#[test]
+fn test_refund() {
+    let mut deps = test_setup(false);
+    let msg = ExecuteMsg::Configure {
+        token: "token0000".to_string(),
+        payment: uusd(),
+        start_time: 10,
+        end_time: 100,
+        raising_amount: Uint128::from(100 * ONE),
+        soft_cap: Uint128::from(60 * ONE),
+        offering_amount: Uint128::from(500 * ONE),
+        vesting_initial: Uint128::from(100000_u128),
+        vesting_time: 200,
+        vesting_start: 0,
+        vesting_cliff: 0,
+        merkle_root: MERKLE_ROOT.to_string(),
+        fcfs_cap: FcfsCap::Flat {
+            amount: Uint128::from(250 * ONE),
+        },
+        finalized: true,
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let msg = ExecuteMsg::Deposit {
+        allocation: Uint128::from(ALLOCATION),
+        proof: test_merkle_proof(),
+    };
+    let info = mock_info("addr0001", &[Coin::new(50 * ONE, "uusd")]);
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(40);
+    execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let info = mock_info("addr0001", &[]);
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Refund {},
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::SaleNotFailed {}, err);
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::Collect {},
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::SaleNotEnded {}, err);
+    // Finalized, but nothing can be harvested while the sale can still fail
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Harvest {},
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::SaleNotEnded {}, err);
+
+    env.block.time = Timestamp::from_seconds(101);
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Harvest {},
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::SaleFailed {}, err);
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::Collect {},
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::SaleFailed {}, err);
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Refund {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![Coin::new(49504950_u128, "uusd")],
+        }))],
+    );
+    let err = execute(deps.as_mut(), env, info, ExecuteMsg::Refund {}).unwrap_err();
+    assert_eq!(ContractError::NoZeroAmount {}, err);
+}
+
+#[test]
+fn test_cancel() {
+    let mut deps = test_setup(true);
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(50);
+
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Cancel {}).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let info = mock_info("addr0000", &[]);
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Cancel {},
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::AlreadyFinalized {}, err);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        configure_msg(false),
+    )
+    .unwrap();
+    execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Cancel {}).unwrap();
+
+    let msg = ExecuteMsg::Deposit {
+        allocation: Uint128::from(ALLOCATION),
+        proof: test_merkle_proof(),
+    };
+    let info = mock_info("addr0001", &[Coin::new(ONE, "uusd")]);
+    let err = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+    assert_eq!(ContractError::Cancelled {}, err);
+
+    let res: RefundsResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::Refunds {}).unwrap()).unwrap();
+    assert_eq!(res.refunded, Uint128::zero());
+    assert_eq!(res.outstanding, Uint128::from(50 * ONE));
+
+    let info = mock_info("addr0001", &[]);
+    let res = execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Refund {}).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![Coin::new(49504950_u128, "uusd")],
+        }))],
+    );
+
+    let res: RefundsResponse =
+        from_binary(&query(deps.as_ref(), env, QueryMsg::Refunds {}).unwrap()).unwrap();
+    assert_eq!(
+        res,
+        RefundsResponse {
+            cancelled: true,
+            refunded: Uint128::from(50 * ONE),
+            outstanding: Uint128::zero(),
+        }
+    );
+}
+
+#[test]
+fn test_cancel_after_harvest() {
+    let mut deps = test_setup(true);
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(50);
+    let info = mock_info("addr0001", &[]);
+    execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Harvest {}).unwrap();
+
+    let owner = mock_info("addr0000", &[]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        configure_msg(false),
+    )
+    .unwrap();
+    // Un-finalizing doesn't reopen cancel once tokens went out
+    let err = execute(deps.as_mut(), env, owner, ExecuteMsg::Cancel {}).unwrap_err();
+    assert_eq!(ContractError::AlreadyClaimed {}, err);
+}
+
 #[test]
 fn test_collect() {
     let mut deps = test_setup(true);
@@ -461,6 +1098,16 @@ fn test_collect() {
             amount: vec![Coin::new(79207920_u128, "uusd")],
         }))],
     );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        configure_msg(false),
+    )
+    .unwrap();
+    let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Cancel {}).unwrap_err();
+    assert_eq!(ContractError::AlreadyCollected {}, err);
 }
 
 pub struct CustomMockQuerier {
@@ -5,12 +5,13 @@ mod testing;
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
     to_binary, Addr, BankMsg, Binary, CanonicalAddr, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env,
-    MessageInfo, Response, StdError, StdResult, Uint128, WasmMsg,
+    MessageInfo, Order, Response, StdError, StdResult, Storage, Uint128, WasmMsg,
 };
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
 use cw20::Cw20ExecuteMsg;
-use cw_storage_plus::{Item, Map};
+use cw_storage_plus::{Bound, Item, Map};
 use schemars::JsonSchema;
+use semver::Version;
 use serde::{Deserialize, Serialize};
 use sha3::Digest;
 use std::convert::TryInto;
@@ -26,6 +27,24 @@ pub enum ContractError {
     Std(#[from] StdError),
     #[error("Unauthorized")]
     Unauthorized {},
+    #[error("Paused")]
+    Paused {},
+    #[error("MigrationWrongContract")]
+    MigrationWrongContract {},
+    #[error("MigrationDowngrade")]
+    MigrationDowngrade {},
+    #[error("Frozen")]
+    Frozen {},
+    #[error("Cancelled")]
+    Cancelled {},
+    #[error("NotCancelled")]
+    NotCancelled {},
+    #[error("AlreadyFinalized")]
+    AlreadyFinalized {},
+    #[error("AlreadyCollected")]
+    AlreadyCollected {},
+    #[error("AlreadyClaimed")]
+    AlreadyClaimed {},
     #[error("NoZeroAmount")]
     NoZeroAmount,
     #[error("NoOtherDenoms")]
@@ -68,9 +87,29 @@ pub enum ExecuteMsg {
         offering_amount: Uint128,
         vesting_initial: Uint128,
         vesting_time: u64,
+        vesting_start: u64,
+        vesting_cliff: u64,
         merkle_root: String,
         finalized: bool,
     },
+    ConfigureRoles {
+        pauser: String,
+        configurer: String,
+        treasury: String,
+    },
+    Pause {
+        paused: bool,
+    },
+    Freeze {
+        user: String,
+    },
+    Unfreeze {
+        user: String,
+    },
+    TransferOwnership {
+        owner: String,
+    },
+    AcceptOwnership {},
     Deposit {
         allocation: Uint128,
         proof: Vec<String>,
@@ -79,6 +118,8 @@ pub enum ExecuteMsg {
         amount: Uint128,
     },
     Harvest {},
+    Refund {},
+    Cancel {},
     Collect {},
     CollectTokens {
         amount: Uint128,
@@ -88,16 +129,35 @@ pub enum ExecuteMsg {
     },
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     State {},
-    UserState { user: String, now: u64 },
+    UserState {
+        user: String,
+        now: u64,
+    },
+    AllUsers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    Frozen {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    Refunds {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct StateResponse {
     pub owner: String,
+    pub pending_owner: Option<String>,
+    pub pauser: String,
+    pub configurer: String,
+    pub treasury: String,
     pub token: String,
     pub start_time: u64,
     pub end_deposit_time: u64,
@@ -106,11 +166,15 @@ pub struct StateResponse {
     pub offering_amount: Uint128,
     pub vesting_initial: Uint128,
     pub vesting_time: u64,
+    pub vesting_start: u64,
+    pub vesting_cliff: u64,
     pub merkle_root: String,
     pub finalized: bool,
+    pub paused: bool,
     pub total_users: u64,
     pub total_amount: Uint128,
     pub total_amount_high: Uint128,
+    pub cancelled: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -122,12 +186,39 @@ pub struct UserStateResponse {
     pub claimable: Uint128,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AllUsersResponse {
+    pub users: Vec<UserStateEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FrozenResponse {
+    pub users: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RefundsResponse {
+    pub cancelled: bool,
+    pub refunded: Uint128,
+    pub outstanding: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserStateEntry {
+    pub user: String,
+    pub state: UserStateResponse,
+}
+
 // STATE
 // -----------------------------------------------------
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
     pub owner: CanonicalAddr,
+    pub pending_owner: Option<CanonicalAddr>, // proposed by transfer_ownership
+    pub pauser: CanonicalAddr,
+    pub configurer: CanonicalAddr,
+    pub treasury: CanonicalAddr, // receives collected funds
     pub token: CanonicalAddr,
     pub start_time: u64,
     pub end_deposit_time: u64,
@@ -135,12 +226,19 @@ pub struct State {
     pub min_price: Uint128,
     pub offering_amount: Uint128,
     pub vesting_initial: Uint128, // vested initially 1e6 = 100%
-    pub vesting_time: u64,        // time past end_time to 100% vested
+    pub vesting_time: u64,        // time past the cliff to 100% vested
+    pub vesting_start: u64, // TGE, nothing is claimable before it, 0 vests from end_withdraw_time
+    pub vesting_cliff: u64, // time past vesting_start before linear vesting
     pub merkle_root: String,
     pub finalized: bool,
+    pub paused: bool,
     pub total_users: u64,
     pub total_amount: Uint128,
     pub total_amount_high: Uint128,
+    pub cancelled: bool, // set by the owner, depositors get refunded
+    pub collected: bool, // deposits were sent to the treasury, the sale can't be cancelled
+    pub harvested: bool, // tokens were sent to a depositor, the sale can't be cancelled
+    pub total_refunded: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
@@ -152,11 +250,36 @@ pub struct UserState {
 
 pub const STATE: Item<State> = Item::new("state");
 pub const USERS_STATE: Map<&Addr, UserState> = Map::new("users");
+// Users that can't deposit or harvest
+pub const FROZEN: Map<&Addr, bool> = Map::new("frozen");
+
+// State as stored by 1.0.0
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct StateV1_0 {
+    owner: CanonicalAddr,
+    token: CanonicalAddr,
+    start_time: u64,
+    end_deposit_time: u64,
+    end_withdraw_time: u64,
+    min_price: Uint128,
+    offering_amount: Uint128,
+    vesting_initial: Uint128,
+    vesting_time: u64,
+    merkle_root: String,
+    finalized: bool,
+    total_users: u64,
+    total_amount: Uint128,
+    total_amount_high: Uint128,
+}
+
+const STATE_V1_0: Item<StateV1_0> = Item::new("state");
 
 // CONTRACT
 // -----------------------------------------------------
 
-const CONTRACT_NAME: &str = "sale";
+const CONTRACT_NAME: &str = "sale-commit";
+// Name stored by releases before 1.1.0
+const LEGACY_CONTRACT_NAME: &str = "sale";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -169,6 +292,10 @@ pub fn instantiate(
     let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
     let state = State {
         owner: sender_addr.clone(),
+        pending_owner: None,
+        pauser: sender_addr.clone(),
+        configurer: sender_addr.clone(),
+        treasury: sender_addr.clone(),
         token: sender_addr.clone(),
         start_time: 0,
         end_deposit_time: 0,
@@ -177,11 +304,18 @@ pub fn instantiate(
         offering_amount: Uint128::zero(),
         vesting_initial: Uint128::zero(),
         vesting_time: 0,
+        vesting_start: 0,
+        vesting_cliff: 0,
         merkle_root: String::new(),
         finalized: false,
+        paused: false,
         total_users: 0,
         total_amount: Uint128::zero(),
         total_amount_high: Uint128::zero(),
+        cancelled: false,
+        collected: false,
+        harvested: false,
+        total_refunded: Uint128::zero(),
     };
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     STATE.save(deps.storage, &state)?;
@@ -209,6 +343,8 @@ pub fn execute(
             offering_amount,
             vesting_initial,
             vesting_time,
+            vesting_start,
+            vesting_cliff,
             merkle_root,
             finalized,
         } => configure(
@@ -223,15 +359,29 @@ pub fn execute(
             offering_amount,
             vesting_initial,
             vesting_time,
+            vesting_start,
+            vesting_cliff,
             merkle_root,
             finalized,
         ),
+        ExecuteMsg::ConfigureRoles {
+            pauser,
+            configurer,
+            treasury,
+        } => configure_roles(deps, env, info, pauser, configurer, treasury),
+        ExecuteMsg::Pause { paused } => pause(deps, env, info, paused),
+        ExecuteMsg::Freeze { user } => freeze(deps, env, info, user, true),
+        ExecuteMsg::Unfreeze { user } => freeze(deps, env, info, user, false),
+        ExecuteMsg::TransferOwnership { owner } => transfer_ownership(deps, env, info, owner),
+        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, env, info),
         ExecuteMsg::Deposit { allocation, proof } => deposit(deps, env, info, allocation, proof),
         ExecuteMsg::Withdraw { amount } => withdraw(deps, env, info, amount),
         ExecuteMsg::Harvest {} => harvest(deps, env, info),
+        ExecuteMsg::Refund {} => refund(deps, env, info),
+        ExecuteMsg::Cancel {} => cancel(deps, env, info),
         ExecuteMsg::Collect {} => collect(deps, env, info),
         ExecuteMsg::CollectTokens { amount } => collect_tokens(deps, env, info, amount),
-        ExecuteMsg::Migrate { new_contract } => migrate(deps, env, info, new_contract),
+        ExecuteMsg::Migrate { new_contract } => migrate_funds(deps, env, info, new_contract),
     }
 }
 
@@ -247,13 +397,15 @@ pub fn configure(
     offering_amount: Uint128,
     vesting_initial: Uint128,
     vesting_time: u64,
+    vesting_start: u64,
+    vesting_cliff: u64,
     merkle_root: String,
     finalized: bool,
 ) -> Result<Response, ContractError> {
     let state = STATE.load(deps.storage)?;
     let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
     let token_addr = deps.api.addr_canonicalize(&token)?;
-    if state.owner != sender_addr {
+    if state.configurer != sender_addr {
         // TODO DEBUG
         return Err(ContractError::Unauthorized {});
     }
@@ -267,6 +419,8 @@ pub fn configure(
         state.offering_amount = offering_amount;
         state.vesting_initial = vesting_initial;
         state.vesting_time = vesting_time;
+        state.vesting_start = vesting_start;
+        state.vesting_cliff = vesting_cliff;
         state.merkle_root = merkle_root.clone();
         state.finalized = finalized;
         Ok(state)
@@ -282,11 +436,142 @@ pub fn configure(
         ("offering_amount", offering_amount.to_string().as_str()),
         ("vesting_initial", vesting_initial.to_string().as_str()),
         ("vesting_time", vesting_time.to_string().as_str()),
+        ("vesting_start", vesting_start.to_string().as_str()),
+        ("vesting_cliff", vesting_cliff.to_string().as_str()),
         ("merkle_root", merkle_root.as_str()),
         ("finalized", finalized.to_string().as_str()),
     ]))
 }
 
+pub fn configure_roles(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    pauser: String,
+    configurer: String,
+    treasury: String,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if state.owner != sender_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let pauser_addr = deps.api.addr_canonicalize(&pauser)?;
+    let configurer_addr = deps.api.addr_canonicalize(&configurer)?;
+    let treasury_addr = deps.api.addr_canonicalize(&treasury)?;
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.pauser = pauser_addr;
+        state.configurer = configurer_addr;
+        state.treasury = treasury_addr;
+        Ok(state)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "configure_roles"),
+        ("pauser", pauser.as_str()),
+        ("configurer", configurer.as_str()),
+        ("treasury", treasury.as_str()),
+    ]))
+}
+
+pub fn pause(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    paused: bool,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if state.pauser != sender_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.paused = paused;
+        Ok(state)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "pause"),
+        ("paused", paused.to_string().as_str()),
+    ]))
+}
+
+pub fn freeze(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    user: String,
+    frozen: bool,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if state.owner != sender_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let user_addr = deps.api.addr_validate(&user)?;
+    if frozen {
+        FROZEN.save(deps.storage, &user_addr, &true)?;
+    } else {
+        FROZEN.remove(deps.storage, &user_addr);
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "freeze"),
+        ("user", user_addr.as_str()),
+        ("frozen", frozen.to_string().as_str()),
+    ]))
+}
+
+pub fn transfer_ownership(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    owner: String,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if state.owner != sender_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let owner_addr = deps.api.addr_canonicalize(&owner)?;
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.pending_owner = Some(owner_addr);
+        Ok(state)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "transfer_ownership"),
+        ("pending_owner", owner.as_str()),
+    ]))
+}
+
+pub fn accept_ownership(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if state.pending_owner != Some(sender_addr.clone()) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.owner = sender_addr;
+        state.pending_owner = None;
+        Ok(state)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "accept_ownership"),
+        ("owner", info.sender.as_str()),
+    ]))
+}
+
 pub fn deposit(
     deps: DepsMut,
     env: Env,
@@ -297,6 +582,15 @@ pub fn deposit(
     let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
     let sender = deps.api.addr_humanize(&sender_addr)?;
     let state = STATE.load(deps.storage)?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
+    if FROZEN.has(deps.storage, &sender) {
+        return Err(ContractError::Frozen {});
+    }
+    if state.cancelled {
+        return Err(ContractError::Cancelled {});
+    }
     if state.start_time == 0 {
         return Err(ContractError::NotConfigured {});
     }
@@ -366,6 +660,12 @@ pub fn withdraw(
     let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
     let sender = deps.api.addr_humanize(&sender_addr)?;
     let state = STATE.load(deps.storage)?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
+    if state.cancelled {
+        return Err(ContractError::Cancelled {});
+    }
     if env.block.time.seconds() < state.end_deposit_time {
         return Err(ContractError::WithdrawNotStarted {});
     }
@@ -437,9 +737,18 @@ pub fn harvest(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, C
     let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
     let sender = deps.api.addr_humanize(&sender_addr)?;
     let state = STATE.load(deps.storage)?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
+    if FROZEN.has(deps.storage, &sender) {
+        return Err(ContractError::Frozen {});
+    }
     if !state.finalized {
         return Err(ContractError::NotFinalized {});
     }
+    if state.cancelled {
+        return Err(ContractError::Cancelled {});
+    }
 
     let mut amount = Uint128::zero();
     USERS_STATE.update(
@@ -456,6 +765,12 @@ pub fn harvest(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, C
             Ok(user_state)
         },
     )?;
+    if !state.harvested {
+        STATE.update(deps.storage, |mut state| -> StdResult<_> {
+            state.harvested = true;
+            Ok(state)
+        })?;
+    }
 
     Ok(Response::new()
         .add_messages(vec![CosmosMsg::Wasm(WasmMsg::Execute {
@@ -473,27 +788,105 @@ pub fn harvest(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, C
         ]))
 }
 
-pub fn collect(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+pub fn refund(deps: DepsMut, _env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
+    if !state.cancelled {
+        return Err(ContractError::NotCancelled {});
+    }
+
+    let mut user_state = USERS_STATE
+        .may_load(deps.storage, &info.sender)?
+        .unwrap_or_default();
+    let amount = user_state.amount;
+    if amount.is_zero() {
+        return Err(ContractError::NoZeroAmount {});
+    }
+    if !user_state.claimed.is_zero() {
+        return Err(ContractError::AlreadyClaimed {});
+    }
+    user_state.amount = Uint128::zero();
+    USERS_STATE.save(deps.storage, &info.sender, &user_state)?;
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.total_amount -= amount;
+        state.total_refunded += amount;
+        Ok(state)
+    })?;
+
+    let amount_after_tax = deduct_tax(
+        deps,
+        Coin {
+            denom: String::from("uusd"),
+            amount,
+        },
+    )?;
+    Ok(Response::new()
+        .add_message(CosmosMsg::Bank(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![amount_after_tax.clone()],
+        }))
+        .add_attributes(vec![
+            ("action", "refund"),
+            ("user", info.sender.as_str()),
+            ("amount", amount_after_tax.amount.to_string().as_str()),
+        ]))
+}
+
+pub fn cancel(deps: DepsMut, _env: Env, info: MessageInfo) -> Result<Response, ContractError> {
     let state = STATE.load(deps.storage)?;
     let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
-    let sender = deps.api.addr_humanize(&sender_addr)?;
     if state.owner != sender_addr {
         return Err(ContractError::Unauthorized {});
     }
+    if state.finalized {
+        return Err(ContractError::AlreadyFinalized {});
+    }
+    if state.collected {
+        return Err(ContractError::AlreadyCollected {});
+    }
+    // Unlike finalized, this can't be undone by configure
+    if state.harvested {
+        return Err(ContractError::AlreadyClaimed {});
+    }
+
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.cancelled = true;
+        Ok(state)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![("action", "cancel")]))
+}
+
+pub fn collect(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if state.owner != sender_addr && state.treasury != sender_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+    if state.cancelled {
+        return Err(ContractError::Cancelled {});
+    }
+    let treasury = deps.api.addr_humanize(&state.treasury)?;
 
     let balance = deps
         .querier
         .query_balance(env.contract.address, "uusd")
         .unwrap();
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.collected = true;
+        Ok(state)
+    })?;
     let balance_after_tax = deduct_tax(deps, balance)?;
     Ok(Response::new()
         .add_message(CosmosMsg::Bank(BankMsg::Send {
-            to_address: sender.to_string(),
+            to_address: treasury.to_string(),
             amount: vec![balance_after_tax.clone()],
         }))
         .add_attributes(vec![
             ("action", "collect"),
-            ("user", sender.to_string().as_str()),
+            ("user", treasury.to_string().as_str()),
             ("amount", balance_after_tax.amount.to_string().as_str()),
         ]))
 }
@@ -506,28 +899,28 @@ pub fn collect_tokens(
 ) -> Result<Response, ContractError> {
     let state = STATE.load(deps.storage)?;
     let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
-    let sender = deps.api.addr_humanize(&sender_addr)?;
-    if state.owner != sender_addr {
+    if state.owner != sender_addr && state.treasury != sender_addr {
         return Err(ContractError::Unauthorized {});
     }
+    let treasury = deps.api.addr_humanize(&state.treasury)?;
 
     Ok(Response::new()
         .add_messages(vec![CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: deps.api.addr_humanize(&state.token)?.to_string(),
             msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                recipient: sender.to_string(),
+                recipient: treasury.to_string(),
                 amount,
             })?,
             funds: vec![],
         })])
         .add_attributes(vec![
             ("action", "collect_tokens"),
-            ("user", sender.to_string().as_str()),
+            ("user", treasury.to_string().as_str()),
             ("amount", amount.to_string().as_str()),
         ]))
 }
 
-pub fn migrate(
+pub fn migrate_funds(
     _deps: DepsMut,
     _env: Env,
     _info: MessageInfo,
@@ -537,10 +930,75 @@ pub fn migrate(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let previous = get_contract_version(deps.storage)?;
+    let previous_version = parse_version(&previous.version)?;
+    let legacy_name =
+        previous.contract == LEGACY_CONTRACT_NAME && previous_version < Version::new(1, 1, 0);
+    if previous.contract != CONTRACT_NAME && !legacy_name {
+        return Err(ContractError::MigrationWrongContract {});
+    }
+    if previous_version > parse_version(CONTRACT_VERSION)? {
+        return Err(ContractError::MigrationDowngrade {});
+    }
+
+    if previous_version < Version::new(1, 1, 0) {
+        migrate_state_v1_0(deps.storage)?;
+    }
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "migrate"),
+        ("from_version", previous.version.as_str()),
+        ("to_version", CONTRACT_VERSION),
+    ]))
+}
+
+// Roles default to the owner
+fn migrate_state_v1_0(storage: &mut dyn Storage) -> StdResult<()> {
+    let legacy = STATE_V1_0.load(storage)?;
+    let state = State {
+        owner: legacy.owner.clone(),
+        pending_owner: None,
+        pauser: legacy.owner.clone(),
+        configurer: legacy.owner.clone(),
+        treasury: legacy.owner,
+        token: legacy.token,
+        start_time: legacy.start_time,
+        end_deposit_time: legacy.end_deposit_time,
+        end_withdraw_time: legacy.end_withdraw_time,
+        min_price: legacy.min_price,
+        offering_amount: legacy.offering_amount,
+        vesting_initial: legacy.vesting_initial,
+        vesting_time: legacy.vesting_time,
+        vesting_start: 0,
+        vesting_cliff: 0,
+        merkle_root: legacy.merkle_root,
+        finalized: legacy.finalized,
+        paused: false,
+        total_users: legacy.total_users,
+        total_amount: legacy.total_amount,
+        total_amount_high: legacy.total_amount_high,
+        cancelled: false,
+        collected: true, // unknown for 1.0.0 sales
+        harvested: legacy.finalized,
+        total_refunded: Uint128::zero(),
+    };
+    STATE.save(storage, &state)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::State {} => to_binary(&query_state(deps)?),
         QueryMsg::UserState { user, now } => to_binary(&query_user_state(deps, user, now)?),
+        QueryMsg::AllUsers { start_after, limit } => {
+            to_binary(&query_all_users(deps, env, start_after, limit)?)
+        }
+        QueryMsg::Frozen { start_after, limit } => {
+            to_binary(&query_frozen(deps, start_after, limit)?)
+        }
+        QueryMsg::Refunds {} => to_binary(&query_refunds(deps)?),
     }
 }
 
@@ -548,6 +1006,13 @@ fn query_state(deps: Deps) -> StdResult<StateResponse> {
     let state = STATE.load(deps.storage)?;
     Ok(StateResponse {
         owner: deps.api.addr_humanize(&state.owner)?.to_string(),
+        pending_owner: state
+            .pending_owner
+            .map(|a| deps.api.addr_humanize(&a).map(|a| a.to_string()))
+            .transpose()?,
+        pauser: deps.api.addr_humanize(&state.pauser)?.to_string(),
+        configurer: deps.api.addr_humanize(&state.configurer)?.to_string(),
+        treasury: deps.api.addr_humanize(&state.treasury)?.to_string(),
         token: deps.api.addr_humanize(&state.token)?.to_string(),
         start_time: state.start_time,
         end_deposit_time: state.end_deposit_time,
@@ -556,11 +1021,24 @@ fn query_state(deps: Deps) -> StdResult<StateResponse> {
         offering_amount: state.offering_amount,
         vesting_initial: state.vesting_initial,
         vesting_time: state.vesting_time,
+        vesting_start: state.vesting_start,
+        vesting_cliff: state.vesting_cliff,
         merkle_root: state.merkle_root,
         finalized: state.finalized,
+        paused: state.paused,
         total_users: state.total_users,
         total_amount: state.total_amount,
         total_amount_high: state.total_amount_high,
+        cancelled: state.cancelled,
+    })
+}
+
+fn query_refunds(deps: Deps) -> StdResult<RefundsResponse> {
+    let state = STATE.load(deps.storage)?;
+    Ok(RefundsResponse {
+        cancelled: state.cancelled,
+        refunded: state.total_refunded,
+        outstanding: state.total_amount,
     })
 }
 
@@ -571,20 +1049,64 @@ fn query_user_state(deps: Deps, user: String, now: u64) -> StdResult<UserStateRe
     let user_state = USERS_STATE
         .may_load(deps.storage, &user)?
         .unwrap_or_default();
-    let (owed, claimable) = user_vesting(&state, &user_state, now);
-    Ok(UserStateResponse {
+    Ok(user_state_response(&state, &user_state, now))
+}
+
+fn query_all_users(
+    deps: Deps,
+    env: Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<AllUsersResponse> {
+    let state = STATE.load(deps.storage)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let users = USERS_STATE
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, user_state) = item?;
+            Ok(UserStateEntry {
+                user: String::from_utf8(key)?,
+                state: user_state_response(&state, &user_state, env.block.time.seconds()),
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(AllUsersResponse { users })
+}
+
+fn query_frozen(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<FrozenResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let users = FROZEN
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(String::from_utf8)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(FrozenResponse { users })
+}
+
+fn user_state_response(state: &State, user_state: &UserState, now: u64) -> UserStateResponse {
+    let (owed, claimable) = user_vesting(state, user_state, now);
+    UserStateResponse {
         amount: user_state.amount,
         amount_high: user_state.amount_high,
         claimed: user_state.claimed,
         owed: owed,
         claimable: claimable,
-    })
+    }
 }
 
 // HELPERS
 // -----------------------------------------------------
 
 const ONE: u128 = 1000000_u128;
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
 
 fn one() -> Uint128 {
     Uint128::from(1000000_u128)
@@ -595,16 +1117,38 @@ fn user_vesting(state: &State, user_state: &UserState, now: u64) -> (Uint128, Ui
         .multiply_ratio(state.total_amount, state.offering_amount)
         .max(state.min_price);
     let owed = user_state.amount.multiply_ratio(ONE, price);
-    let vesting_progress = now
-        .saturating_sub(state.end_withdraw_time)
-        .min(state.vesting_time);
-    let claimable = owed.multiply_ratio(state.vesting_initial, one())
-        + owed
-            .multiply_ratio(one().saturating_sub(state.vesting_initial), one())
-            .multiply_ratio(vesting_progress, state.vesting_time);
+    if now < state.vesting_start {
+        return (owed, Uint128::zero());
+    }
+    let start = if state.vesting_start == 0 {
+        state.end_withdraw_time
+    } else {
+        state.vesting_start
+    };
+    let vesting_amount = owed.multiply_ratio(one().saturating_sub(state.vesting_initial), one());
+    let vested = if state.vesting_time == 0 {
+        // Without linear vesting everything unlocks at the cliff
+        if now >= start + state.vesting_cliff {
+            vesting_amount
+        } else {
+            Uint128::zero()
+        }
+    } else {
+        let vesting_progress = now
+            .saturating_sub(start + state.vesting_cliff)
+            .min(state.vesting_time);
+        vesting_amount.multiply_ratio(vesting_progress, state.vesting_time)
+    };
+    let claimable = owed.multiply_ratio(state.vesting_initial, one()) + vested;
     return (owed, claimable);
 }
 
+fn parse_version(version: &str) -> StdResult<Version> {
+    version
+        .parse()
+        .map_err(|_| StdError::generic_err(format!("Invalid version: {}", version)))
+}
+
 fn merkle_verify(
     merkle_root: String,
     user_input: String,
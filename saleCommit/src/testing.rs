@@ -53,6 +53,8 @@ fn test_setup(deposit: bool) -> OwnedDeps<MockStorage, MockApi, CustomMockQuerie
             offering_amount: Uint128::from(500 * ONE),
             vesting_initial: Uint128::from(100000_u128),
             vesting_time: 200,
+            vesting_start: 0,
+            vesting_cliff: 0,
             merkle_root: MERKLE_ROOT.to_string(),
             finalized: false,
         };
@@ -71,26 +73,31 @@ fn test_setup(deposit: bool) -> OwnedDeps<MockStorage, MockApi, CustomMockQuerie
             execute(deps.as_mut(), env, info.clone(), msg).unwrap();
         }
         {
-            let msg = ExecuteMsg::Configure {
-                token: "token0000".to_string(),
-                start_time: 10,
-                end_deposit_time: 100,
-                end_withdraw_time: 200,
-                min_price: Uint128::from(0 * ONE),
-                offering_amount: Uint128::from(500 * ONE),
-                vesting_initial: Uint128::from(100000_u128),
-                vesting_time: 200,
-                merkle_root: MERKLE_ROOT.to_string(),
-                finalized: true,
-            };
             let info = mock_info("addr0000", &[]);
-            execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+            execute(deps.as_mut(), mock_env(), info.clone(), configure_msg(true)).unwrap();
         }
     }
 
     deps
 }
 
+fn configure_msg(finalized: bool) -> ExecuteMsg {
+    ExecuteMsg::Configure {
+        token: "token0000".to_string(),
+        start_time: 10,
+        end_deposit_time: 100,
+        end_withdraw_time: 200,
+        min_price: Uint128::from(0 * ONE),
+        offering_amount: Uint128::from(500 * ONE),
+        vesting_initial: Uint128::from(100000_u128),
+        vesting_time: 200,
+        vesting_start: 0,
+        vesting_cliff: 0,
+        merkle_root: MERKLE_ROOT.to_string(),
+        finalized,
+    }
+}
+
 #[test]
 fn test_instantiate() {
     let mut deps = mock_dependencies(&[]);
@@ -126,6 +133,8 @@ fn test_configure_error_not_owner() {
         offering_amount: Uint128::from(500 * ONE),
         vesting_initial: Uint128::from(100000_u128),
         vesting_time: 200,
+        vesting_start: 0,
+        vesting_cliff: 0,
         merkle_root: MERKLE_ROOT.to_string(),
         finalized: false,
     };
@@ -146,6 +155,8 @@ fn test_configure() {
         offering_amount: Uint128::from(500 * ONE),
         vesting_initial: Uint128::from(100000_u128),
         vesting_time: 200,
+        vesting_start: 0,
+        vesting_cliff: 0,
         merkle_root: MERKLE_ROOT.to_string(),
         finalized: true,
     };
@@ -312,6 +323,50 @@ fn test_deposit() {
     }
 }
 
+#[test]
+fn test_all_users() {
+    let mut deps = test_setup(false);
+    let msg = ExecuteMsg::Deposit {
+        allocation: Uint128::from(ALLOCATION),
+        proof: test_merkle_proof(),
+    };
+    let info = mock_info("addr0001", &[Coin::new(50 * ONE, "uusd")]);
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(40);
+    execute(deps.as_mut(), env, info.clone(), msg).unwrap();
+    USERS_STATE
+        .save(
+            deps.as_mut().storage,
+            &Addr::unchecked("addr0002"),
+            &UserState {
+                amount: Uint128::from(10 * ONE),
+                amount_high: Uint128::zero(),
+                claimed: Uint128::zero(),
+            },
+        )
+        .unwrap();
+
+    let msg = QueryMsg::AllUsers {
+        start_after: None,
+        limit: Some(1),
+    };
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let value: AllUsersResponse = from_binary(&res).unwrap();
+    assert_eq!(1, value.users.len());
+    assert_eq!("addr0001", value.users[0].user);
+    assert_eq!("50000000", value.users[0].state.amount.to_string());
+
+    let msg = QueryMsg::AllUsers {
+        start_after: Some("addr0001".to_string()),
+        limit: None,
+    };
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let value: AllUsersResponse = from_binary(&res).unwrap();
+    assert_eq!(1, value.users.len());
+    assert_eq!("addr0002", value.users[0].user);
+    assert_eq!("10000000", value.users[0].state.amount.to_string());
+}
+
 #[test]
 fn test_withdraw_error_not_started() {
     let mut deps = test_setup(true);
@@ -404,6 +459,8 @@ fn test_harvest_error_not_finalized() {
             offering_amount: Uint128::from(500 * ONE),
             vesting_initial: Uint128::from(100000_u128),
             vesting_time: 200,
+            vesting_start: 0,
+            vesting_cliff: 0,
             merkle_root: MERKLE_ROOT.to_string(),
             finalized: false,
         };
@@ -457,6 +514,8 @@ fn test_harvest() {
             offering_amount: Uint128::from(15000 * ONE),
             vesting_initial: Uint128::from(1000000_u128), // 100%
             vesting_time: 1,
+            vesting_start: 0,
+            vesting_cliff: 0,
             merkle_root: MERKLE_ROOT.to_string(),
             finalized: true,
         };
@@ -506,6 +565,8 @@ fn test_harvest_min_price() {
             offering_amount: Uint128::from(500 * ONE),
             vesting_initial: Uint128::from(100000_u128),
             vesting_time: 200,
+            vesting_start: 0,
+            vesting_cliff: 0,
             merkle_root: MERKLE_ROOT.to_string(),
             finalized: true,
         };
@@ -540,6 +601,354 @@ fn test_harvest_min_price() {
     );
 }
 
+#[test]
+fn test_vesting_cliff() {
+    let mut deps = test_setup(true);
+    let msg = ExecuteMsg::Configure {
+        token: "token0000".to_string(),
+        start_time: 10,
+        end_deposit_time: 100,
+        end_withdraw_time: 200,
+        min_price: Uint128::zero(),
+        offering_amount: Uint128::from(500 * ONE),
+        vesting_initial: Uint128::from(100000_u128),
+        vesting_time: 200,
+        vesting_start: 300,
+        vesting_cliff: 100,
+        merkle_root: MERKLE_ROOT.to_string(),
+        finalized: true,
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let claimable = |now: u64| {
+        let msg = QueryMsg::UserState {
+            user: "addr0001".to_string(),
+            now,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        from_binary::<UserStateResponse>(&res).unwrap().claimable
+    };
+    assert_eq!(claimable(299), Uint128::zero());
+    assert_eq!(claimable(300), Uint128::from(50 * ONE));
+    assert_eq!(claimable(400), Uint128::from(50 * ONE));
+    assert_eq!(claimable(500), Uint128::from(275 * ONE));
+    assert_eq!(claimable(600), Uint128::from(500 * ONE));
+}
+
+#[test]
+fn test_vesting_cliff_unlock() {
+    let mut deps = test_setup(true);
+    let msg = ExecuteMsg::Configure {
+        token: "token0000".to_string(),
+        start_time: 10,
+        end_deposit_time: 100,
+        end_withdraw_time: 200,
+        min_price: Uint128::zero(),
+        offering_amount: Uint128::from(500 * ONE),
+        vesting_initial: Uint128::from(100000_u128),
+        vesting_time: 0,
+        vesting_start: 300,
+        vesting_cliff: 100,
+        merkle_root: MERKLE_ROOT.to_string(),
+        finalized: true,
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let claimable = |now: u64| {
+        let msg = QueryMsg::UserState {
+            user: "addr0001".to_string(),
+            now,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        from_binary::<UserStateResponse>(&res).unwrap().claimable
+    };
+    assert_eq!(claimable(299), Uint128::zero());
+    assert_eq!(claimable(300), Uint128::from(50 * ONE));
+    assert_eq!(claimable(399), Uint128::from(50 * ONE));
+    assert_eq!(claimable(400), Uint128::from(500 * ONE));
+}
+
+#[test]
+fn test_freeze() {
+    let mut deps = test_setup(false);
+    let msg = ExecuteMsg::Freeze {
+        user: "addr0001".to_string(),
+    };
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::Deposit {
+        allocation: Uint128::from(ALLOCATION),
+        proof: test_merkle_proof(),
+    };
+    let info = mock_info("addr0001", &[Coin::new(50 * ONE, "uusd")]);
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(40);
+    let err = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Frozen {}, err);
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Frozen {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let value: FrozenResponse = from_binary(&res).unwrap();
+    assert_eq!(vec!["addr0001".to_string()], value.users);
+
+    let unfreeze = ExecuteMsg::Unfreeze {
+        user: "addr0001".to_string(),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        unfreeze,
+    )
+    .unwrap();
+    execute(deps.as_mut(), env, info, msg).unwrap();
+}
+
+#[test]
+fn test_transfer_ownership() {
+    let mut deps = test_setup(false);
+    let msg = ExecuteMsg::TransferOwnership {
+        owner: "addr0007".to_string(),
+    };
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::AcceptOwnership {};
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let info = mock_info("addr0007", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let value: StateResponse = from_binary(&res).unwrap();
+    assert_eq!("addr0007", value.owner);
+    assert_eq!(None, value.pending_owner);
+}
+
+#[test]
+fn test_configure_roles() {
+    let mut deps = test_setup(true);
+    let msg = ExecuteMsg::ConfigureRoles {
+        pauser: "pauser0000".to_string(),
+        configurer: "configurer0000".to_string(),
+        treasury: "treasury0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::Pause { paused: true };
+    let info = mock_info("addr0000", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let info = mock_info("pauser0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::Harvest {};
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(ContractError::Paused {}, err);
+
+    // Funds go to the treasury, the pauser can't collect
+    let msg = ExecuteMsg::Collect {};
+    let info = mock_info("pauser0000", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "treasury0000".to_string(),
+            amount: vec![Coin::new(79207920_u128, "uusd")],
+        }))],
+    );
+}
+
+#[test]
+fn test_migrate_version() {
+    let mut deps = mock_dependencies(&[]);
+    cw2::set_contract_version(deps.as_mut().storage, "sale", "1.0.0").unwrap();
+    let owner = deps.as_ref().api.addr_canonicalize("addr0000").unwrap();
+    let token = deps.as_ref().api.addr_canonicalize("token0000").unwrap();
+    STATE_V1_0
+        .save(
+            deps.as_mut().storage,
+            &StateV1_0 {
+                owner,
+                token,
+                start_time: 10,
+                end_deposit_time: 100,
+                end_withdraw_time: 200,
+                min_price: Uint128::zero(),
+                offering_amount: Uint128::from(500 * ONE),
+                vesting_initial: Uint128::from(100000_u128),
+                vesting_time: 200,
+                merkle_root: MERKLE_ROOT.to_string(),
+                finalized: false,
+                total_users: 1,
+                total_amount: Uint128::from(50 * ONE),
+                total_amount_high: Uint128::from(50 * ONE),
+            },
+        )
+        .unwrap();
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "migrate"),
+            attr("from_version", "1.0.0"),
+            attr("to_version", CONTRACT_VERSION),
+        ]
+    );
+    let version = cw2::get_contract_version(deps.as_ref().storage).unwrap();
+    assert_eq!(CONTRACT_NAME, version.contract);
+    assert_eq!(CONTRACT_VERSION, version.version);
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let value: StateResponse = from_binary(&res).unwrap();
+    assert_eq!("addr0000", value.owner);
+    assert_eq!("addr0000", value.treasury);
+    assert!(!value.paused);
+    assert_eq!("50000000", value.total_amount.to_string());
+
+    cw2::set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "9.0.0").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert_eq!(ContractError::MigrationDowngrade {}, err);
+
+    cw2::set_contract_version(deps.as_mut().storage, "ts-tiers", "1.0.0").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert_eq!(ContractError::MigrationWrongContract {}, err);
+}
+
+#[test]
+fn test_cancel() {
+    let mut deps = test_setup(true);
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(150);
+
+    let info = mock_info("addr0001", &[]);
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Refund {},
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::NotCancelled {}, err);
+    let err = execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Cancel {}).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let info = mock_info("addr0000", &[]);
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Cancel {},
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::AlreadyFinalized {}, err);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        configure_msg(false),
+    )
+    .unwrap();
+    execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Cancel {}).unwrap();
+
+    let msg = ExecuteMsg::Withdraw {
+        amount: Uint128::from(8_u128),
+    };
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+    assert_eq!(ContractError::Cancelled {}, err);
+    // Finalizing afterwards doesn't allow harvesting
+    let owner = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), env.clone(), owner, configure_msg(true)).unwrap();
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Harvest {},
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::Cancelled {}, err);
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Refund {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "refund"),
+            attr("user", "addr0001"),
+            attr("amount", "49504950"),
+        ]
+    );
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![Coin::new(49504950_u128, "uusd")],
+        }))],
+    );
+    let err = execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Refund {}).unwrap_err();
+    assert_eq!(ContractError::NoZeroAmount {}, err);
+
+    let res: RefundsResponse =
+        from_binary(&query(deps.as_ref(), env, QueryMsg::Refunds {}).unwrap()).unwrap();
+    assert_eq!(
+        res,
+        RefundsResponse {
+            cancelled: true,
+            refunded: Uint128::from(50 * ONE),
+            outstanding: Uint128::zero(),
+        }
+    );
+}
+
+#[test]
+fn test_cancel_after_harvest() {
+    let mut deps = test_setup(true);
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(250);
+    let info = mock_info("addr0001", &[]);
+    execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Harvest {}).unwrap();
+
+    let owner = mock_info("addr0000", &[]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        configure_msg(false),
+    )
+    .unwrap();
+    // Un-finalizing doesn't reopen cancel once tokens went out
+    let err = execute(deps.as_mut(), env, owner, ExecuteMsg::Cancel {}).unwrap_err();
+    assert_eq!(ContractError::AlreadyClaimed {}, err);
+}
+
 #[test]
 fn test_collect() {
     let mut deps = test_setup(true);
@@ -561,6 +970,16 @@ fn test_collect() {
             amount: vec![Coin::new(79207920_u128, "uusd")],
         }))],
     );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        configure_msg(false),
+    )
+    .unwrap();
+    let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Cancel {}).unwrap_err();
+    assert_eq!(ContractError::AlreadyCollected {}, err);
 }
 
 pub struct CustomMockQuerier {
@@ -13,13 +13,18 @@ async function main() {
   const tx = await sendTransaction([
     new MsgInstantiateContract(walletAddress, walletAddress, codeId, {
       token: "terra1td743l5k5cmfy7tqq202g7vkmdvq35q48u2jfm",
+      payment: { native: { denom: "uusd" } },
       start_time: 1649777400,
       end_time: 1649863800,
       raising_amount: "300000" + "000000",
+      soft_cap: "0",
       offering_amount: "5000000" + "000000",
       vesting_initial: "100000000000",
       vesting_time: 15552000,
+      vesting_start: 0,
+      vesting_cliff: 0,
       merkle_root: "",
+      fcfs_cap: { flat: { amount: "250" + "000000" } },
     }),
   ]);
   console.log("tx", tx);
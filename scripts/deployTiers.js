@@ -16,8 +16,16 @@ async function main() {
 
   const tx = await sendTransaction([
     new MsgInstantiateContract(walletAddress, walletAddress, codeId, {
-      token: tokenAddress,
+      asset: { token: { address: tokenAddress } },
       locked_period: 604800,
+      unbonding_period: 604800,
+      tiers: [
+        { min_balance: "2500" + "000000", multiplier: 1 },
+        { min_balance: "7500" + "000000", multiplier: 2 },
+        { min_balance: "25000" + "000000", multiplier: 4 },
+        { min_balance: "75000" + "000000", multiplier: 8 },
+        { min_balance: "150000" + "000000", multiplier: 12 },
+      ],
     }),
   ]);
   console.log("tx", tx);
@@ -3,7 +3,12 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use thorstarter_terra_tiers::{StateResponse, UserStateResponse, ExecuteMsg, InstantiateMsg, QueryMsg, State};
+use thorstarter_terra_tiers::{
+    AllUsersResponse, AssetsResponse, BalanceResponse, ExecuteMsg, FrozenResponse, HooksResponse,
+    InstantiateMsg, MemberListResponse, MemberResponse, MigrateMsg, MigrationContractResponse,
+    QueryMsg, State, StateResponse, TotalWeightResponse, UnbondingsResponse, UserAssetsResponse,
+    UserPowerResponse, UserStateResponse, UserTierResponse,
+};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -13,8 +18,22 @@ fn main() {
 
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(State), &out_dir);
     export_schema(&schema_for!(StateResponse), &out_dir);
     export_schema(&schema_for!(UserStateResponse), &out_dir);
+    export_schema(&schema_for!(AllUsersResponse), &out_dir);
+    export_schema(&schema_for!(UserTierResponse), &out_dir);
+    export_schema(&schema_for!(UserPowerResponse), &out_dir);
+    export_schema(&schema_for!(UnbondingsResponse), &out_dir);
+    export_schema(&schema_for!(MigrationContractResponse), &out_dir);
+    export_schema(&schema_for!(HooksResponse), &out_dir);
+    export_schema(&schema_for!(MemberResponse), &out_dir);
+    export_schema(&schema_for!(MemberListResponse), &out_dir);
+    export_schema(&schema_for!(TotalWeightResponse), &out_dir);
+    export_schema(&schema_for!(AssetsResponse), &out_dir);
+    export_schema(&schema_for!(UserAssetsResponse), &out_dir);
+    export_schema(&schema_for!(BalanceResponse), &out_dir);
+    export_schema(&schema_for!(FrozenResponse), &out_dir);
 }
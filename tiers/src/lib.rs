@@ -4,14 +4,17 @@ mod testing;
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    from_binary, to_binary, Addr, Binary, CanonicalAddr, CosmosMsg, Deps, DepsMut, Env,
-    MessageInfo, QueryRequest, Response, StdError, StdResult, Uint128, WasmMsg, WasmQuery,
+    from_binary, to_binary, Addr, BankMsg, Binary, CanonicalAddr, Coin, ContractResult, CosmosMsg,
+    Decimal, Deps, DepsMut, Env, MessageInfo, Order, QueryRequest, Reply, Response, StdError,
+    StdResult, Storage, SubMsg, Uint128, WasmMsg, WasmQuery,
 };
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
 use cw20::{BalanceResponse as CW20BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg};
-use cw_storage_plus::{Item, Map};
+use cw_storage_plus::{Bound, Item, Map, SnapshotItem, SnapshotMap, Strategy};
 use schemars::JsonSchema;
+use semver::Version;
 use serde::{Deserialize, Serialize};
+use terra_cosmwasm::TerraQuerier;
 use thiserror::Error;
 
 // ERRORS
@@ -29,6 +32,30 @@ pub enum ContractError {
     UnbondBalanceTooLow {},
     #[error("UnbondBefore7Days")]
     UnbondBefore7Days {},
+    #[error("InvalidTiers")]
+    InvalidTiers {},
+    #[error("NoZeroAmount")]
+    NoZeroAmount {},
+    #[error("InvalidPenaltyRate")]
+    InvalidPenaltyRate {},
+    #[error("InvalidDuration")]
+    InvalidDuration {},
+    #[error("InvalidAsset")]
+    InvalidAsset {},
+    #[error("MigrationWrongContract")]
+    MigrationWrongContract {},
+    #[error("MigrationDowngrade")]
+    MigrationDowngrade {},
+    #[error("InvalidDelegation")]
+    InvalidDelegation {},
+    #[error("TooManyDelegators")]
+    TooManyDelegators {},
+    #[error("Frozen")]
+    Frozen {},
+    #[error("NotEmergency")]
+    NotEmergency {},
+    #[error("Emergency")]
+    Emergency {},
     // See https://docs.rs/thiserror/1.0.21/thiserror/
 }
 
@@ -37,50 +64,328 @@ pub enum ContractError {
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
-    pub token: String,
+    pub asset: StakeAsset,
     pub locked_period: u64,
+    pub unbonding_period: u64,
+    pub tiers: Vec<Tier>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
     Receive(Cw20ReceiveMsg),
-    Configure { paused: bool, locked_period: u64 },
-    TransferOwnership { owner: Addr },
-    Unbond { amount: Uint128 },
-    UnbondNow { amount: Uint128 },
-    Migrate { new_contract: String },
+    BondNative {},
+    BondNativeLocked {
+        duration: u64,
+    },
+    FundRewardsNative {
+        duration: u64,
+    },
+    Configure {
+        locked_period: u64,
+        unbonding_period: u64,
+        penalty_rate: u64,
+        penalty_destination: PenaltyDestination,
+    },
+    ConfigureTiers {
+        tiers: Vec<Tier>,
+    },
+    ConfigureBoost {
+        max_lock_duration: u64,
+        max_boost: Decimal,
+    },
+    ConfigureMigration {
+        contract: String,
+        allowed: bool,
+    },
+    AddHook {
+        addr: String,
+    },
+    RemoveHook {
+        addr: String,
+    },
+    ConfigureAsset {
+        asset: String,
+        weight: Decimal,
+    },
+    ConfigureRoles {
+        pauser: String,
+        configurer: String,
+        treasury: String,
+    },
+    Pause {
+        paused: bool,
+    },
+    Emergency {
+        emergency: bool,
+    },
+    Freeze {
+        user: String,
+    },
+    Unfreeze {
+        user: String,
+    },
+    TransferOwnership {
+        owner: Addr,
+    },
+    AcceptOwnership {},
+    Unbond {
+        amount: Uint128,
+    },
+    UnbondNow {
+        amount: Uint128,
+    },
+    UnbondAsset {
+        asset: String,
+        amount: Uint128,
+    },
+    Claim {},
+    ClaimRewards {},
+    EmergencyWithdraw {},
+    Delegate {
+        to: String,
+    },
+    Undelegate {},
+    Migrate {
+        new_contract: String,
+    },
+    MigrateUsers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Cw20HookMsg {
     Bond {},
+    BondFor {
+        beneficiary: String,
+    },
+    BondLocked {
+        duration: u64,
+    },
+    MigrateBond {
+        user: String,
+        last_deposit: u64,
+        lots: Vec<Lot>,
+    },
+    FundRewards {
+        duration: u64,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct MigrateMsg {}
 
+// Sent to hook contracts when a balance changes, same format as cw4 groups
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MemberChangedExecuteMsg {
+    MemberChangedHook(MemberChangedHookMsg),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MemberChangedHookMsg {
+    pub diffs: Vec<MemberDiff>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MemberDiff {
+    pub key: String,
+    pub old: Option<u64>, // None when the balance was 0
+    pub new: Option<u64>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     State {},
-    UserState { user: String },
+    UserState {
+        user: String,
+    },
+    AllUsers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    UserTier {
+        user: String,
+    },
+    UserPower {
+        user: String,
+        time: Option<u64>, // defaults to now
+    },
+    Unbondings {
+        user: String,
+    },
+    MigrationContract {
+        contract: String,
+    },
+    Hooks {},
+    Assets {},
+    UserAssets {
+        user: String,
+    },
+    BalanceAt {
+        user: String,
+        time: u64,
+    },
+    TotalBalanceAt {
+        time: u64,
+    },
+    AverageBalance {
+        user: String,
+        from: u64,
+        to: u64,
+    },
+    // cw4 group interface, weights are bonded balances
+    Member {
+        addr: String,
+        at_height: Option<u64>,
+    },
+    ListMembers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    TotalWeight {},
+    Frozen {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct StateResponse {
     pub owner: String,
-    pub token: String,
+    pub pending_owner: Option<String>,
+    pub pauser: String,
+    pub configurer: String,
+    pub treasury: String,
+    pub asset: StakeAsset,
     pub paused: bool,
+    pub emergency: bool,
     pub locked_period: u64,
+    pub unbonding_period: u64,
+    pub penalty_rate: u64,
+    pub penalty_destination: PenaltyDestination,
     pub total_balance: Uint128,
+    pub tiers: Vec<Tier>,
+    pub max_lock_duration: u64,
+    pub max_boost: Decimal,
+    pub reward_amount: Uint128,
+    pub reward_end: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct UserStateResponse {
     pub last_deposit: u64,
     pub balance: Uint128,
+    pub unlocked_balance: Uint128,
+    pub lots: Vec<Lot>,
+    pub pending_rewards: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AllUsersResponse {
+    pub users: Vec<UserStateEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FrozenResponse {
+    pub users: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserStateEntry {
+    pub user: String,
+    pub state: UserStateResponse,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserTierResponse {
+    pub balance: Uint128,
+    pub power: Uint128, // own power unless delegated, plus delegated power, used for the tier
+    pub own_power: Uint128, // boosted balance plus weighted extra assets
+    pub delegated_power: Uint128, // own power of the users delegating to this one
+    pub delegated_to: Option<String>,
+    pub tier: u64,                 // 0 = no tier, 1 = first entry of `State.tiers`
+    pub multiplier: u64,           // 0 when not in any tier
+    pub next_tier_amount: Uint128, // power missing to reach the next tier, 0 at the top
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserPowerResponse {
+    pub balance: Uint128,
+    pub power: Uint128,
+    pub own_power: Uint128,
+    pub delegated_power: Uint128,
+    pub delegated_to: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UnbondingsResponse {
+    pub unbondings: Vec<Unbonding>,
+    pub claimable: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrationContractResponse {
+    pub allowed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MemberResponse {
+    pub weight: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MemberListResponse {
+    pub members: Vec<Member>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Member {
+    pub addr: String,
+    pub weight: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TotalWeightResponse {
+    pub weight: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HooksResponse {
+    pub hooks: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AssetsResponse {
+    pub assets: Vec<AssetWeight>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AssetWeight {
+    pub asset: String,
+    pub weight: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserAssetsResponse {
+    pub assets: Vec<UserAsset>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserAsset {
+    pub asset: String,
+    pub amount: Uint128,
+    pub lots: Vec<Lot>,
+    pub weight: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BalanceResponse {
+    pub balance: Uint128,
 }
 
 // STATE
@@ -89,20 +394,144 @@ pub struct UserStateResponse {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
     pub owner: CanonicalAddr,
-    pub token: CanonicalAddr,
+    pub pending_owner: Option<CanonicalAddr>, // proposed by transfer_ownership
+    pub pauser: CanonicalAddr,
+    pub configurer: CanonicalAddr, // periods, penalty and tiers
+    pub treasury: CanonicalAddr,   // receives penalties
+    pub asset: StakeAsset,         // bonded, and paid out as rewards
     pub paused: bool,
+    pub emergency: bool, // users can withdraw everything without penalty or lock
     pub locked_period: u64,
+    pub unbonding_period: u64, // time between unbond and claim
+    pub penalty_rate: u64,     // unbond_now penalty in basis points
+    pub penalty_destination: PenaltyDestination,
     pub total_balance: Uint128,
+    pub tiers: Vec<Tier>,       // sorted by ascending min_balance
+    pub max_lock_duration: u64, // longest lock, and the one getting the full boost
+    pub max_boost: Decimal,     // extra power of a lot with max_lock_duration left
+    pub reward_index: Decimal,  // rewards per bonded token since instantiate
+    pub reward_amount: Uint128, // funded rewards not yet streamed into reward_index
+    pub reward_end: u64,
+    pub reward_last_update: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StakeAsset {
+    Token { address: Addr },
+    Native { denom: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PenaltyDestination {
+    Treasury {},
+    Burn {},
+    Redistribute {}, // to the remaining bonders, through the reward index
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Tier {
+    pub min_balance: Uint128,
+    pub multiplier: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
 pub struct UserState {
     pub last_deposit: u64,
     pub balance: Uint128,
+    // Fields below are missing for users who bonded before 1.1.0
+    #[serde(default)]
+    pub lots: Vec<Lot>, // sorted by ascending unlock_time, amounts add up to balance
+    #[serde(default)]
+    pub reward_index: Decimal,
+    #[serde(default)]
+    pub pending_rewards: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Lot {
+    pub amount: Uint128,
+    pub unlock_time: u64,
+}
+
+// Extra asset bonded by a user, each bond unlocks `locked_period` after it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct AssetBond {
+    pub amount: Uint128,
+    pub lots: Vec<Lot>, // sorted by ascending unlock_time, amounts add up to amount
+}
+
+// Balance of a user since `time`, and the sum of balance * seconds held before it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct Observation {
+    pub time: u64,
+    pub balance: Uint128,
+    pub cumulative: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Unbonding {
+    pub amount: Uint128,
+    pub release_time: u64,
 }
 
 pub const STATE: Item<State> = Item::new("state");
 pub const USERS_STATE: Map<&Addr, UserState> = Map::new("users");
+pub const UNBONDINGS: Map<&Addr, Vec<Unbonding>> = Map::new("unbondings");
+// Contracts stakes can be migrated to, and accepted from
+pub const MIGRATION_CONTRACTS: Map<&Addr, bool> = Map::new("migration_contracts");
+// Contracts notified of balance changes
+pub const HOOKS: Map<&Addr, bool> = Map::new("hooks");
+// Extra cw20 assets that can be bonded, and their weight towards tier power
+pub const ASSETS: Map<&Addr, Decimal> = Map::new("assets");
+// Keyed by (user, asset)
+pub const ASSET_BONDS: Map<(&Addr, &Addr), AssetBond> = Map::new("asset_bonds");
+// Delegate of each user, and the reverse index keyed by (delegate, user)
+pub const DELEGATIONS: Map<&Addr, Addr> = Map::new("delegations");
+pub const DELEGATORS: Map<(&Addr, &Addr), bool> = Map::new("delegators");
+// Users that can't bond and have no tier power
+pub const FROZEN: Map<&Addr, bool> = Map::new("frozen");
+// Snapshots of `UserState.balance` and `State.total_balance`, keyed by block time (seconds)
+pub const BALANCES: SnapshotMap<&Addr, Uint128> = SnapshotMap::new(
+    "balances",
+    "balances__checkpoints",
+    "balances__changelog",
+    Strategy::EveryBlock,
+);
+pub const TOTAL_BALANCE: SnapshotItem<Uint128> = SnapshotItem::new(
+    "total_balance",
+    "total_balance__checkpoints",
+    "total_balance__changelog",
+    Strategy::EveryBlock,
+);
+pub const OBSERVATIONS: SnapshotMap<&Addr, Observation> = SnapshotMap::new(
+    "observations",
+    "observations__checkpoints",
+    "observations__changelog",
+    Strategy::EveryBlock,
+);
+// Non-zero balances of users not frozen as cw4 weights, keyed by block height like cw4 groups
+pub const MEMBERS: SnapshotMap<&Addr, u64> = SnapshotMap::new(
+    "members",
+    "members__checkpoints",
+    "members__changelog",
+    Strategy::EveryBlock,
+);
+// Sum of the MEMBERS weights
+pub const TOTAL_WEIGHT: Item<Uint128> = Item::new("total_weight");
+
+// State as stored by 1.0.0
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct StateV1_0 {
+    owner: CanonicalAddr,
+    token: CanonicalAddr,
+    paused: bool,
+    locked_period: u64,
+    total_balance: Uint128,
+}
+
+const STATE_V1_0: Item<StateV1_0> = Item::new("state");
 
 // CONTRACT
 // -----------------------------------------------------
@@ -113,18 +542,37 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
+    validate_tiers(&msg.tiers)?;
     let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
-    let token_addr = deps.api.addr_canonicalize(&msg.token)?;
+    let asset_name = match &msg.asset {
+        StakeAsset::Token { address } => deps.api.addr_validate(address.as_str())?.to_string(),
+        StakeAsset::Native { denom } => denom.clone(),
+    };
     let state = State {
         owner: sender_addr.clone(),
-        token: token_addr.clone(),
+        pending_owner: None,
+        pauser: sender_addr.clone(),
+        configurer: sender_addr.clone(),
+        treasury: sender_addr.clone(),
+        asset: msg.asset,
         paused: false,
+        emergency: false,
         locked_period: msg.locked_period,
+        unbonding_period: msg.unbonding_period,
+        penalty_rate: 5000,
+        penalty_destination: PenaltyDestination::Treasury {},
         total_balance: Uint128::zero(),
+        tiers: msg.tiers,
+        max_lock_duration: ONE_YEAR,
+        max_boost: Decimal::zero(),
+        reward_index: Decimal::zero(),
+        reward_amount: Uint128::zero(),
+        reward_end: env.block.time.seconds(),
+        reward_last_update: env.block.time.seconds(),
     };
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     STATE.save(deps.storage, &state)?;
@@ -132,8 +580,12 @@ pub fn instantiate(
     Ok(Response::new().add_attributes(vec![
         ("action", "instantiate"),
         ("owner", sender_addr.to_string().as_str()),
-        ("token", token_addr.to_string().as_str()),
+        ("asset", asset_name.as_str()),
         ("locked_period", msg.locked_period.to_string().as_str()),
+        (
+            "unbonding_period",
+            msg.unbonding_period.to_string().as_str(),
+        ),
     ]))
 }
 
@@ -146,14 +598,61 @@ pub fn execute(
 ) -> Result<Response, ContractError> {
     match msg {
         ExecuteMsg::Configure {
-            paused,
             locked_period,
-        } => configure(deps, env, info, paused, locked_period),
+            unbonding_period,
+            penalty_rate,
+            penalty_destination,
+        } => configure(
+            deps,
+            env,
+            info,
+            locked_period,
+            unbonding_period,
+            penalty_rate,
+            penalty_destination,
+        ),
+        ExecuteMsg::ConfigureTiers { tiers } => configure_tiers(deps, env, info, tiers),
+        ExecuteMsg::ConfigureBoost {
+            max_lock_duration,
+            max_boost,
+        } => configure_boost(deps, env, info, max_lock_duration, max_boost),
+        ExecuteMsg::ConfigureMigration { contract, allowed } => {
+            configure_migration(deps, env, info, contract, allowed)
+        }
+        ExecuteMsg::AddHook { addr } => configure_hook(deps, env, info, addr, true),
+        ExecuteMsg::RemoveHook { addr } => configure_hook(deps, env, info, addr, false),
+        ExecuteMsg::ConfigureAsset { asset, weight } => {
+            configure_asset(deps, env, info, asset, weight)
+        }
+        ExecuteMsg::ConfigureRoles {
+            pauser,
+            configurer,
+            treasury,
+        } => configure_roles(deps, env, info, pauser, configurer, treasury),
+        ExecuteMsg::Pause { paused } => pause(deps, env, info, paused),
+        ExecuteMsg::Emergency { emergency } => set_emergency(deps, env, info, emergency),
+        ExecuteMsg::Freeze { user } => freeze(deps, env, info, user, true),
+        ExecuteMsg::Unfreeze { user } => freeze(deps, env, info, user, false),
         ExecuteMsg::TransferOwnership { owner } => transfer_ownership(deps, env, info, owner),
+        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, env, info),
         ExecuteMsg::Receive(msg) => receive_cw20(deps, env, info, msg),
+        ExecuteMsg::BondNative {} => bond_native(deps, env, info, None),
+        ExecuteMsg::BondNativeLocked { duration } => bond_native(deps, env, info, Some(duration)),
+        ExecuteMsg::FundRewardsNative { duration } => {
+            fund_rewards_native(deps, env, info, duration)
+        }
         ExecuteMsg::Unbond { amount } => unbond(deps, env, info, amount),
         ExecuteMsg::UnbondNow { amount } => unbond_now(deps, env, info, amount),
-        ExecuteMsg::Migrate { new_contract } => migrate(deps, env, info, new_contract),
+        ExecuteMsg::UnbondAsset { asset, amount } => unbond_asset(deps, env, info, asset, amount),
+        ExecuteMsg::Claim {} => claim(deps, env, info),
+        ExecuteMsg::ClaimRewards {} => claim_rewards(deps, env, info),
+        ExecuteMsg::EmergencyWithdraw {} => emergency_withdraw(deps, env, info),
+        ExecuteMsg::Delegate { to } => delegate(deps, env, info, to),
+        ExecuteMsg::Undelegate {} => undelegate(deps, env, info),
+        ExecuteMsg::Migrate { new_contract } => migrate_stake(deps, env, info, new_contract),
+        ExecuteMsg::MigrateUsers { start_after, limit } => {
+            migrate_users(deps, env, info, start_after, limit)
+        }
     }
 }
 
@@ -161,260 +660,1894 @@ pub fn configure(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
-    paused: bool,
     locked_period: u64,
+    unbonding_period: u64,
+    penalty_rate: u64,
+    penalty_destination: PenaltyDestination,
 ) -> Result<Response, ContractError> {
     let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
     let state = STATE.load(deps.storage)?;
-    if state.owner != sender_addr {
+    if state.configurer != sender_addr {
         return Err(ContractError::Unauthorized {});
     }
+    if penalty_rate > BPS {
+        return Err(ContractError::InvalidPenaltyRate {});
+    }
 
+    let penalty_destination_name = match penalty_destination {
+        PenaltyDestination::Treasury {} => "treasury",
+        PenaltyDestination::Burn {} => "burn",
+        PenaltyDestination::Redistribute {} => "redistribute",
+    };
     STATE.update(deps.storage, |mut state| -> StdResult<_> {
-        state.paused = paused;
         state.locked_period = locked_period;
+        state.unbonding_period = unbonding_period;
+        state.penalty_rate = penalty_rate;
+        state.penalty_destination = penalty_destination;
         Ok(state)
     })?;
 
     Ok(Response::new().add_attributes(vec![
         ("action", "configure"),
-        ("paused", paused.to_string().as_str()),
         ("locked_period", locked_period.to_string().as_str()),
+        ("unbonding_period", unbonding_period.to_string().as_str()),
+        ("penalty_rate", penalty_rate.to_string().as_str()),
+        ("penalty_destination", penalty_destination_name),
     ]))
 }
 
-pub fn transfer_ownership(
+pub fn configure_tiers(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
-    owner: Addr,
+    tiers: Vec<Tier>,
 ) -> Result<Response, ContractError> {
     let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
     let state = STATE.load(deps.storage)?;
-    if state.owner != sender_addr {
+    if state.configurer != sender_addr {
         return Err(ContractError::Unauthorized {});
     }
+    validate_tiers(&tiers)?;
 
-    let owner_addr = deps.api.addr_canonicalize(owner.as_str())?;
+    let tiers_count = tiers.len();
     STATE.update(deps.storage, |mut state| -> StdResult<_> {
-        state.owner = owner_addr;
+        state.tiers = tiers;
         Ok(state)
     })?;
 
     Ok(Response::new().add_attributes(vec![
-        ("action", "transfer_ownership"),
-        ("owner", owner.to_string().as_str()),
+        ("action", "configure_tiers"),
+        ("tiers", tiers_count.to_string().as_str()),
     ]))
 }
 
-pub fn receive_cw20(
+pub fn configure_boost(
     deps: DepsMut,
-    env: Env,
+    _env: Env,
     info: MessageInfo,
-    msg: Cw20ReceiveMsg,
+    max_lock_duration: u64,
+    max_boost: Decimal,
 ) -> Result<Response, ContractError> {
     let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
     let state = STATE.load(deps.storage)?;
-
-    match from_binary(&msg.msg) {
-        Ok(Cw20HookMsg::Bond {}) => {
-            if state.token != sender_addr {
-                return Err(ContractError::Unauthorized {});
-            }
-            let cw20_sender_addr = deps.api.addr_canonicalize(&msg.sender)?;
-            bond(deps, env, cw20_sender_addr, msg.amount)
-        }
-        Err(_) => Err(ContractError::Std(StdError::generic_err(
-            "missing message data",
-        ))),
+    if state.configurer != sender_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+    if max_lock_duration == 0 {
+        return Err(ContractError::InvalidDuration {});
     }
+
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.max_lock_duration = max_lock_duration;
+        state.max_boost = max_boost;
+        Ok(state)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "configure_boost"),
+        ("max_lock_duration", max_lock_duration.to_string().as_str()),
+        ("max_boost", max_boost.to_string().as_str()),
+    ]))
 }
 
-pub fn bond(
+pub fn configure_migration(
     deps: DepsMut,
-    env: Env,
-    sender_addr: CanonicalAddr,
-    amount: Uint128,
+    _env: Env,
+    info: MessageInfo,
+    contract: String,
+    allowed: bool,
 ) -> Result<Response, ContractError> {
-    let sender = deps.api.addr_humanize(&sender_addr)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
     let state = STATE.load(deps.storage)?;
-    if state.paused {
-        return Err(ContractError::Paused {});
+    if state.owner != sender_addr {
+        return Err(ContractError::Unauthorized {});
     }
 
-    let new_user_state =
-        USERS_STATE.update(deps.storage, &sender, |maybe_user_state| -> StdResult<_> {
-            let mut user_state = maybe_user_state.unwrap_or_default();
-            user_state.balance += amount;
-            user_state.last_deposit = env.block.time.seconds();
-            Ok(user_state)
-        })?;
-
-    STATE.update(deps.storage, |mut state| -> StdResult<_> {
-        state.total_balance += amount;
-        Ok(state)
-    })?;
+    let contract_addr = deps.api.addr_validate(&contract)?;
+    if allowed {
+        MIGRATION_CONTRACTS.save(deps.storage, &contract_addr, &true)?;
+    } else {
+        MIGRATION_CONTRACTS.remove(deps.storage, &contract_addr);
+    }
 
     Ok(Response::new().add_attributes(vec![
-        ("action", "bond"),
-        ("user", sender.to_string().as_str()),
-        ("tokens", amount.to_string().as_str()),
-        ("balance", new_user_state.balance.to_string().as_str()),
+        ("action", "configure_migration"),
+        ("contract", contract_addr.as_str()),
+        ("allowed", allowed.to_string().as_str()),
     ]))
 }
 
-pub fn unbond(
+pub fn configure_hook(
     deps: DepsMut,
-    env: Env,
+    _env: Env,
     info: MessageInfo,
-    amount: Uint128,
+    addr: String,
+    enabled: bool,
 ) -> Result<Response, ContractError> {
-    let contract_addr = deps
-        .api
-        .addr_canonicalize(env.contract.address.to_string().as_str())?;
     let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
-    let sender = deps.api.addr_humanize(&sender_addr)?;
     let state = STATE.load(deps.storage)?;
-    if state.paused {
-        return Err(ContractError::Paused {});
+    if state.owner != sender_addr {
+        return Err(ContractError::Unauthorized {});
     }
 
-    let new_user_state = USERS_STATE.update(
-        deps.storage,
-        &sender,
-        |maybe_user_state| -> Result<_, ContractError> {
-            let mut user_state = maybe_user_state.unwrap();
-            if user_state.balance < amount {
-                return Err(ContractError::UnbondBalanceTooLow {});
-            }
-            if env.block.time.seconds() < user_state.last_deposit + state.locked_period {
-                return Err(ContractError::UnbondBefore7Days {});
-            }
-            user_state.balance -= amount;
-            Ok(user_state)
-        },
-    )?;
-
-    STATE.update(deps.storage, |mut state| -> StdResult<_> {
-        state.total_balance -= amount;
-        Ok(state)
-    })?;
+    let hook_addr = deps.api.addr_validate(&addr)?;
+    if enabled {
+        HOOKS.save(deps.storage, &hook_addr, &true)?;
+    } else {
+        HOOKS.remove(deps.storage, &hook_addr);
+    }
 
-    Ok(Response::new()
-        .add_messages(vec![CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: deps.api.addr_humanize(&state.token)?.to_string(),
-            msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                recipient: sender.to_string(),
-                amount,
-            })?,
-            funds: vec![],
-        })])
-        .add_attributes(vec![
-            ("action", "unbond"),
-            ("user", sender.to_string().as_str()),
-            ("change", amount.to_string().as_str()),
-            ("balance", new_user_state.balance.to_string().as_str()),
-        ]))
+    Ok(Response::new().add_attributes(vec![
+        ("action", if enabled { "add_hook" } else { "remove_hook" }),
+        ("hook", hook_addr.as_str()),
+    ]))
 }
 
-pub fn unbond_now(
+pub fn freeze(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    amount: Uint128,
+    user: String,
+    frozen: bool,
 ) -> Result<Response, ContractError> {
     let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
-    let sender = deps.api.addr_humanize(&sender_addr)?;
     let state = STATE.load(deps.storage)?;
+    if state.owner != sender_addr {
+        return Err(ContractError::Unauthorized {});
+    }
 
-    let new_user_state = USERS_STATE.update(
-        deps.storage,
-        &sender,
-        |maybe_user_state| -> Result<_, ContractError> {
-            let mut user_state = maybe_user_state.unwrap();
-            if user_state.balance < amount {
-                return Err(ContractError::UnbondBalanceTooLow {});
-            }
-            user_state.balance -= amount;
-            Ok(user_state)
-        },
-    )?;
-
-    STATE.update(deps.storage, |mut state| -> StdResult<_> {
-        state.total_balance -= amount;
-        Ok(state)
-    })?;
+    let user_addr = deps.api.addr_validate(&user)?;
+    let attributes = vec![
+        ("action", "freeze"),
+        ("user", user_addr.as_str()),
+        ("frozen", if frozen { "true" } else { "false" }),
+    ];
+    if FROZEN.has(deps.storage, &user_addr) == frozen {
+        return Ok(Response::new().add_attributes(attributes));
+    }
+    let balance = USERS_STATE
+        .may_load(deps.storage, &user_addr)?
+        .map(|u| u.balance)
+        .unwrap_or_default();
+    let hooks = if frozen {
+        let hooks = member_changed_hooks(deps.storage, &user_addr, balance, Uint128::zero())?;
+        FROZEN.save(deps.storage, &user_addr, &true)?;
+        save_member(deps.storage, &user_addr, None, env.block.height)?;
+        hooks
+    } else {
+        FROZEN.remove(deps.storage, &user_addr);
+        let weight = member_weight(balance);
+        save_member(deps.storage, &user_addr, weight, env.block.height)?;
+        member_changed_hooks(deps.storage, &user_addr, Uint128::zero(), balance)?
+    };
 
     Ok(Response::new()
-        .add_messages(vec![
-            CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: deps.api.addr_humanize(&state.token)?.to_string(),
-                msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                    recipient: sender.to_string(),
-                    amount: amount / Uint128::from(2u128),
-                })?,
-                funds: vec![],
-            }),
-            CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: deps.api.addr_humanize(&state.token)?.to_string(),
-                msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                    recipient: deps.api.addr_humanize(&state.owner)?.to_string(),
-                    amount: amount - (amount / Uint128::from(2u128)),
-                })?,
-                funds: vec![],
-            }),
-        ])
-        .add_attributes(vec![
-            ("action", "unbond_now"),
-            ("user", sender.to_string().as_str()),
-            ("change", amount.to_string().as_str()),
-            ("balance", new_user_state.balance.to_string().as_str()),
-        ]))
+        .add_submessages(hooks)
+        .add_attributes(attributes))
 }
 
-pub fn migrate(
-    _deps: DepsMut,
+pub fn configure_asset(
+    deps: DepsMut,
     _env: Env,
-    _info: MessageInfo,
-    _new_contract: String,
+    info: MessageInfo,
+    asset: String,
+    weight: Decimal,
 ) -> Result<Response, ContractError> {
-    Err(ContractError::Std(StdError::generic_err("unimplemented")))
-}
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let state = STATE.load(deps.storage)?;
+    if state.owner != sender_addr {
+        return Err(ContractError::Unauthorized {});
+    }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::State {} => to_binary(&query_state(deps)?),
-        QueryMsg::UserState { user } => to_binary(&query_user_state(deps, user)?),
+    let asset_addr = deps.api.addr_validate(&asset)?;
+    if is_stake_token(&state, &asset_addr) {
+        return Err(ContractError::InvalidAsset {});
     }
+    // Assets are never removed so bonds can always be unbonded, a zero weight disables them
+    ASSETS.save(deps.storage, &asset_addr, &weight)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "configure_asset"),
+        ("asset", asset_addr.as_str()),
+        ("weight", weight.to_string().as_str()),
+    ]))
 }
 
-fn query_state(deps: Deps) -> StdResult<StateResponse> {
+pub fn configure_roles(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    pauser: String,
+    configurer: String,
+    treasury: String,
+) -> Result<Response, ContractError> {
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let state = STATE.load(deps.storage)?;
+    if state.owner != sender_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let pauser_addr = deps.api.addr_canonicalize(&pauser)?;
+    let configurer_addr = deps.api.addr_canonicalize(&configurer)?;
+    let treasury_addr = deps.api.addr_canonicalize(&treasury)?;
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.pauser = pauser_addr;
+        state.configurer = configurer_addr;
+        state.treasury = treasury_addr;
+        Ok(state)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "configure_roles"),
+        ("pauser", pauser.as_str()),
+        ("configurer", configurer.as_str()),
+        ("treasury", treasury.as_str()),
+    ]))
+}
+
+pub fn pause(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    paused: bool,
+) -> Result<Response, ContractError> {
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let state = STATE.load(deps.storage)?;
+    if state.pauser != sender_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.paused = paused;
+        Ok(state)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "pause"),
+        ("paused", paused.to_string().as_str()),
+    ]))
+}
+
+// Entering emergency also pauses, bonds stay blocked until it's left even if resumed
+pub fn set_emergency(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    emergency: bool,
+) -> Result<Response, ContractError> {
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let state = STATE.load(deps.storage)?;
+    if state.owner != sender_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.emergency = emergency;
+        if emergency {
+            state.paused = true;
+        }
+        Ok(state)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "emergency"),
+        ("emergency", emergency.to_string().as_str()),
+    ]))
+}
+
+pub fn transfer_ownership(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    owner: Addr,
+) -> Result<Response, ContractError> {
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let state = STATE.load(deps.storage)?;
+    if state.owner != sender_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let owner_addr = deps.api.addr_canonicalize(owner.as_str())?;
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.pending_owner = Some(owner_addr);
+        Ok(state)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "transfer_ownership"),
+        ("pending_owner", owner.to_string().as_str()),
+    ]))
+}
+
+pub fn accept_ownership(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let state = STATE.load(deps.storage)?;
+    if state.pending_owner != Some(sender_addr.clone()) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.owner = sender_addr;
+        state.pending_owner = None;
+        Ok(state)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "accept_ownership"),
+        ("owner", info.sender.as_str()),
+    ]))
+}
+
+pub fn receive_cw20(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+
+    match from_binary(&msg.msg) {
+        Ok(Cw20HookMsg::Bond {}) if ASSETS.has(deps.storage, &info.sender) => {
+            let cw20_sender = deps.api.addr_validate(&msg.sender)?;
+            bond_asset(deps, env, cw20_sender, info.sender, msg.amount)
+        }
+        Ok(Cw20HookMsg::Bond {}) => {
+            if !is_stake_token(&state, &info.sender) {
+                return Err(ContractError::Unauthorized {});
+            }
+            let cw20_sender_addr = deps.api.addr_canonicalize(&msg.sender)?;
+            let last_deposit = env.block.time.seconds();
+            let lots = vec![Lot {
+                amount: msg.amount,
+                unlock_time: last_deposit + state.locked_period,
+            }];
+            bond(deps, env, cw20_sender_addr, lots, last_deposit)
+        }
+        Ok(Cw20HookMsg::BondFor { beneficiary }) => {
+            if !is_stake_token(&state, &info.sender) {
+                return Err(ContractError::Unauthorized {});
+            }
+            let beneficiary_addr = deps.api.addr_canonicalize(&beneficiary)?;
+            let last_deposit = env.block.time.seconds();
+            let lots = vec![Lot {
+                amount: msg.amount,
+                unlock_time: last_deposit + state.locked_period,
+            }];
+            bond(deps, env, beneficiary_addr, lots, last_deposit)
+        }
+        Ok(Cw20HookMsg::BondLocked { duration }) => {
+            if !is_stake_token(&state, &info.sender) {
+                return Err(ContractError::Unauthorized {});
+            }
+            validate_lock_duration(&state, duration)?;
+            let cw20_sender_addr = deps.api.addr_canonicalize(&msg.sender)?;
+            let last_deposit = env.block.time.seconds();
+            let lots = vec![Lot {
+                amount: msg.amount,
+                unlock_time: last_deposit + duration,
+            }];
+            bond(deps, env, cw20_sender_addr, lots, last_deposit)
+        }
+        Ok(Cw20HookMsg::MigrateBond {
+            user,
+            last_deposit,
+            lots,
+        }) => {
+            if !is_stake_token(&state, &info.sender) {
+                return Err(ContractError::Unauthorized {});
+            }
+            let cw20_sender = deps.api.addr_validate(&msg.sender)?;
+            if !MIGRATION_CONTRACTS.has(deps.storage, &cw20_sender) {
+                return Err(ContractError::Unauthorized {});
+            }
+            if msg.amount != lots.iter().map(|l| l.amount).sum() {
+                return Err(ContractError::Std(StdError::generic_err(
+                    "lots don't match amount",
+                )));
+            }
+            let user_addr = deps.api.addr_canonicalize(&user)?;
+            bond(deps, env, user_addr, lots, last_deposit)
+        }
+        Ok(Cw20HookMsg::FundRewards { duration }) => {
+            if !is_stake_token(&state, &info.sender) {
+                return Err(ContractError::Unauthorized {});
+            }
+            let cw20_sender = deps.api.addr_validate(&msg.sender)?;
+            fund_rewards(deps, env, cw20_sender, msg.amount, duration)
+        }
+        Err(_) => Err(ContractError::Std(StdError::generic_err(
+            "missing message data",
+        ))),
+    }
+}
+
+pub fn fund_rewards(
+    deps: DepsMut,
+    env: Env,
+    funder: Addr,
+    amount: Uint128,
+    duration: u64,
+) -> Result<Response, ContractError> {
+    if duration == 0 {
+        return Err(ContractError::InvalidDuration {});
+    }
+
+    // Whatever is left of the current period gets spread over the new one
+    let mut state = update_rewards(deps.storage, &env)?;
+    state.reward_amount += amount;
+    state.reward_end = env.block.time.seconds() + duration;
+    state.reward_last_update = env.block.time.seconds();
+    STATE.save(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "fund_rewards"),
+        ("funder", funder.as_str()),
+        ("amount", amount.to_string().as_str()),
+        ("duration", duration.to_string().as_str()),
+        ("reward_amount", state.reward_amount.to_string().as_str()),
+    ]))
+}
+
+pub fn fund_rewards_native(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    duration: u64,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    let amount = native_funds(&state, &info)?;
+    fund_rewards(deps, env, info.sender, amount, duration)
+}
+
+pub fn bond(
+    deps: DepsMut,
+    env: Env,
+    sender_addr: CanonicalAddr,
+    lots: Vec<Lot>,
+    last_deposit: u64,
+) -> Result<Response, ContractError> {
+    let sender = deps.api.addr_humanize(&sender_addr)?;
+    let state = update_rewards(deps.storage, &env)?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
+    if state.emergency {
+        return Err(ContractError::Emergency {});
+    }
+    if FROZEN.has(deps.storage, &sender) {
+        return Err(ContractError::Frozen {});
+    }
+
+    let amount: Uint128 = lots.iter().map(|l| l.amount).sum();
+    let new_user_state =
+        USERS_STATE.update(deps.storage, &sender, |maybe_user_state| -> StdResult<_> {
+            let mut user_state = maybe_user_state.unwrap_or_default();
+            sync_user_state(&state, &mut user_state);
+            user_state.balance += amount;
+            user_state.last_deposit = user_state.last_deposit.max(last_deposit);
+            for lot in lots {
+                add_lot(&mut user_state.lots, lot);
+            }
+            Ok(user_state)
+        })?;
+
+    let new_state = STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.total_balance += amount;
+        Ok(state)
+    })?;
+    snapshot_balances(
+        deps.storage,
+        &env,
+        &sender,
+        new_user_state.balance,
+        new_state.total_balance,
+    )?;
+    let hooks = member_changed_hooks(
+        deps.storage,
+        &sender,
+        new_user_state.balance - amount,
+        new_user_state.balance,
+    )?;
+
+    Ok(Response::new().add_submessages(hooks).add_attributes(vec![
+        ("action", "bond"),
+        ("user", sender.to_string().as_str()),
+        ("tokens", amount.to_string().as_str()),
+        ("balance", new_user_state.balance.to_string().as_str()),
+    ]))
+}
+
+pub fn bond_native(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    duration: Option<u64>,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    let amount = native_funds(&state, &info)?;
+    if let Some(duration) = duration {
+        validate_lock_duration(&state, duration)?;
+    }
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let last_deposit = env.block.time.seconds();
+    let lots = vec![Lot {
+        amount,
+        unlock_time: last_deposit + duration.unwrap_or(state.locked_period),
+    }];
+    bond(deps, env, sender_addr, lots, last_deposit)
+}
+
+pub fn bond_asset(
+    deps: DepsMut,
+    env: Env,
+    user: Addr,
+    asset: Addr,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
+    if state.emergency {
+        return Err(ContractError::Emergency {});
+    }
+    if FROZEN.has(deps.storage, &user) {
+        return Err(ContractError::Frozen {});
+    }
+
+    let unlock_time = env.block.time.seconds() + state.locked_period;
+    let asset_bond = ASSET_BONDS.update(
+        deps.storage,
+        (&user, &asset),
+        |asset_bond| -> StdResult<_> {
+            let mut asset_bond = asset_bond.unwrap_or_default();
+            asset_bond.amount += amount;
+            add_lot(
+                &mut asset_bond.lots,
+                Lot {
+                    amount,
+                    unlock_time,
+                },
+            );
+            Ok(asset_bond)
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "bond_asset"),
+        ("user", user.as_str()),
+        ("asset", asset.as_str()),
+        ("amount", amount.to_string().as_str()),
+        ("balance", asset_bond.amount.to_string().as_str()),
+    ]))
+}
+
+pub fn unbond(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let sender = deps.api.addr_humanize(&sender_addr)?;
+    let state = update_rewards(deps.storage, &env)?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
+
+    let now = env.block.time.seconds();
+    let new_user_state = USERS_STATE.update(
+        deps.storage,
+        &sender,
+        |maybe_user_state| -> Result<_, ContractError> {
+            let mut user_state = maybe_user_state.unwrap();
+            sync_user_state(&state, &mut user_state);
+            if user_state.balance < amount {
+                return Err(ContractError::UnbondBalanceTooLow {});
+            }
+            if unlocked_balance(&user_state.lots, now) < amount {
+                return Err(ContractError::UnbondBefore7Days {});
+            }
+            take_lots(&mut user_state.lots, amount, false);
+            user_state.balance -= amount;
+            Ok(user_state)
+        },
+    )?;
+    let locked_balance = new_user_state.balance - unlocked_balance(&new_user_state.lots, now);
+
+    let new_state = STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.total_balance -= amount;
+        Ok(state)
+    })?;
+    snapshot_balances(
+        deps.storage,
+        &env,
+        &sender,
+        new_user_state.balance,
+        new_state.total_balance,
+    )?;
+    let hooks = member_changed_hooks(
+        deps.storage,
+        &sender,
+        new_user_state.balance + amount,
+        new_user_state.balance,
+    )?;
+    if new_user_state.balance.is_zero() {
+        remove_delegation(deps.storage, &sender)?;
+    }
+
+    let release_time = env.block.time.seconds() + state.unbonding_period;
+    UNBONDINGS.update(deps.storage, &sender, |unbondings| -> StdResult<_> {
+        let mut unbondings = unbondings.unwrap_or_default();
+        unbondings.push(Unbonding {
+            amount,
+            release_time,
+        });
+        Ok(unbondings)
+    })?;
+
+    Ok(Response::new().add_submessages(hooks).add_attributes(vec![
+        ("action", "unbond"),
+        ("user", sender.to_string().as_str()),
+        ("change", amount.to_string().as_str()),
+        ("balance", new_user_state.balance.to_string().as_str()),
+        ("locked_balance", locked_balance.to_string().as_str()),
+        ("release_time", release_time.to_string().as_str()),
+    ]))
+}
+
+pub fn unbond_asset(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
+    if amount.is_zero() {
+        return Err(ContractError::NoZeroAmount {});
+    }
+
+    let asset_addr = deps.api.addr_validate(&asset)?;
+    let key = (&info.sender, &asset_addr);
+    let mut asset_bond = ASSET_BONDS.may_load(deps.storage, key)?.unwrap_or_default();
+    if asset_bond.amount < amount {
+        return Err(ContractError::UnbondBalanceTooLow {});
+    }
+    if unlocked_balance(&asset_bond.lots, env.block.time.seconds()) < amount {
+        return Err(ContractError::UnbondBefore7Days {});
+    }
+    take_lots(&mut asset_bond.lots, amount, false);
+    asset_bond.amount -= amount;
+    if asset_bond.amount.is_zero() {
+        ASSET_BONDS.remove(deps.storage, key);
+    } else {
+        ASSET_BONDS.save(deps.storage, key, &asset_bond)?;
+    }
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: asset_addr.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: info.sender.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }))
+        .add_attributes(vec![
+            ("action", "unbond_asset"),
+            ("user", info.sender.as_str()),
+            ("asset", asset_addr.as_str()),
+            ("amount", amount.to_string().as_str()),
+            ("balance", asset_bond.amount.to_string().as_str()),
+        ]))
+}
+
+pub fn claim(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let sender = deps.api.addr_humanize(&sender_addr)?;
+    let state = STATE.load(deps.storage)?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
+
+    let unbondings = UNBONDINGS
+        .may_load(deps.storage, &sender)?
+        .unwrap_or_default();
+    let (released, pending): (Vec<_>, Vec<_>) = unbondings
+        .into_iter()
+        .partition(|u| u.release_time <= env.block.time.seconds());
+    let amount: Uint128 = released.iter().map(|u| u.amount).sum();
+    if amount.is_zero() {
+        return Err(ContractError::NoZeroAmount {});
+    }
+    if pending.is_empty() {
+        UNBONDINGS.remove(deps.storage, &sender);
+    } else {
+        UNBONDINGS.save(deps.storage, &sender, &pending)?;
+    }
+
+    Ok(Response::new()
+        .add_message(transfer_stake(deps.as_ref(), &state, &sender, amount)?)
+        .add_attributes(vec![
+            ("action", "claim"),
+            ("user", sender.to_string().as_str()),
+            ("amount", amount.to_string().as_str()),
+        ]))
+}
+
+pub fn unbond_now(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let sender = deps.api.addr_humanize(&sender_addr)?;
+    let state = update_rewards(deps.storage, &env)?;
+
+    let mut new_user_state = USERS_STATE.update(
+        deps.storage,
+        &sender,
+        |maybe_user_state| -> Result<_, ContractError> {
+            let mut user_state = maybe_user_state.unwrap();
+            if user_state.balance < amount {
+                return Err(ContractError::UnbondBalanceTooLow {});
+            }
+            sync_user_state(&state, &mut user_state);
+            // The whole amount pays the penalty, so keep the earliest unlocking lots
+            take_lots(&mut user_state.lots, amount, true);
+            user_state.balance -= amount;
+            Ok(user_state)
+        },
+    )?;
+
+    let penalty = amount.multiply_ratio(state.penalty_rate, BPS);
+    let mut redistributed = false;
+    let new_state = STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.total_balance -= amount;
+        // The penalized user's remaining balance gets no share of it
+        let others_balance = state.total_balance - new_user_state.balance;
+        if state.penalty_destination == (PenaltyDestination::Redistribute {})
+            && !others_balance.is_zero()
+        {
+            state.reward_index = state.reward_index + Decimal::from_ratio(penalty, others_balance);
+            redistributed = true;
+        }
+        Ok(state)
+    })?;
+    if redistributed {
+        new_user_state.reward_index = new_state.reward_index;
+        USERS_STATE.save(deps.storage, &sender, &new_user_state)?;
+    }
+    snapshot_balances(
+        deps.storage,
+        &env,
+        &sender,
+        new_user_state.balance,
+        new_state.total_balance,
+    )?;
+
+    let hooks = member_changed_hooks(
+        deps.storage,
+        &sender,
+        new_user_state.balance + amount,
+        new_user_state.balance,
+    )?;
+    if new_user_state.balance.is_zero() {
+        remove_delegation(deps.storage, &sender)?;
+    }
+    let mut messages = vec![];
+    if amount > penalty {
+        messages.push(transfer_stake(
+            deps.as_ref(),
+            &state,
+            &sender,
+            amount - penalty,
+        )?);
+    }
+    if !penalty.is_zero() && !redistributed {
+        let treasury = deps.api.addr_humanize(&state.treasury)?;
+        messages.push(match state.penalty_destination {
+            PenaltyDestination::Treasury {} => {
+                transfer_stake(deps.as_ref(), &state, &treasury, penalty)?
+            }
+            PenaltyDestination::Burn {} => burn_stake(&state, penalty)?,
+            // Nobody left to redistribute to
+            PenaltyDestination::Redistribute {} => {
+                transfer_stake(deps.as_ref(), &state, &treasury, penalty)?
+            }
+        });
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(hooks)
+        .add_attributes(vec![
+            ("action", "unbond_now"),
+            ("user", sender.to_string().as_str()),
+            ("change", amount.to_string().as_str()),
+            ("penalty", penalty.to_string().as_str()),
+            ("balance", new_user_state.balance.to_string().as_str()),
+        ]))
+}
+
+// Returns the whole balance, pending unbondings, rewards and extra assets
+pub fn emergency_withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let sender = deps.api.addr_humanize(&sender_addr)?;
+    let state = update_rewards(deps.storage, &env)?;
+    if !state.emergency {
+        return Err(ContractError::NotEmergency {});
+    }
+
+    let mut user_state = USERS_STATE
+        .may_load(deps.storage, &sender)?
+        .unwrap_or_default();
+    sync_user_state(&state, &mut user_state);
+    let balance = user_state.balance;
+    let rewards = user_state.pending_rewards;
+    let unbonding: Uint128 = UNBONDINGS
+        .may_load(deps.storage, &sender)?
+        .unwrap_or_default()
+        .iter()
+        .map(|u| u.amount)
+        .sum();
+    let asset_bonds = ASSET_BONDS
+        .prefix(&sender)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (key, asset_bond) = item?;
+            Ok((Addr::unchecked(String::from_utf8(key)?), asset_bond.amount))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    let amount = balance + unbonding + rewards;
+    if amount.is_zero() && asset_bonds.is_empty() {
+        return Err(ContractError::NoZeroAmount {});
+    }
+
+    USERS_STATE.remove(deps.storage, &sender);
+    UNBONDINGS.remove(deps.storage, &sender);
+    let new_state = STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.total_balance -= balance;
+        Ok(state)
+    })?;
+    snapshot_balances(
+        deps.storage,
+        &env,
+        &sender,
+        Uint128::zero(),
+        new_state.total_balance,
+    )?;
+
+    let hooks = member_changed_hooks(deps.storage, &sender, balance, Uint128::zero())?;
+    remove_delegation(deps.storage, &sender)?;
+    let mut messages = vec![];
+    if !amount.is_zero() {
+        messages.push(transfer_stake(deps.as_ref(), &state, &sender, amount)?);
+    }
+    for (asset, asset_amount) in asset_bonds {
+        ASSET_BONDS.remove(deps.storage, (&sender, &asset));
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: asset.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: sender.to_string(),
+                amount: asset_amount,
+            })?,
+            funds: vec![],
+        }));
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(hooks)
+        .add_attributes(vec![
+            ("action", "emergency_withdraw"),
+            ("user", sender.to_string().as_str()),
+            ("amount", amount.to_string().as_str()),
+            ("rewards", rewards.to_string().as_str()),
+        ]))
+}
+
+pub fn claim_rewards(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let sender = deps.api.addr_humanize(&sender_addr)?;
+    let state = update_rewards(deps.storage, &env)?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
+
+    let mut amount = Uint128::zero();
+    USERS_STATE.update(
+        deps.storage,
+        &sender,
+        |maybe_user_state| -> Result<_, ContractError> {
+            let mut user_state = maybe_user_state.unwrap_or_default();
+            sync_user_state(&state, &mut user_state);
+            amount = user_state.pending_rewards;
+            if amount.is_zero() {
+                return Err(ContractError::NoZeroAmount {});
+            }
+            user_state.pending_rewards = Uint128::zero();
+            Ok(user_state)
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_message(transfer_stake(deps.as_ref(), &state, &sender, amount)?)
+        .add_attributes(vec![
+            ("action", "claim_rewards"),
+            ("user", sender.to_string().as_str()),
+            ("amount", amount.to_string().as_str()),
+        ]))
+}
+
+pub fn delegate(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    to: String,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
+    let to_addr = deps.api.addr_validate(&to)?;
+    if to_addr == info.sender {
+        return Err(ContractError::InvalidDelegation {});
+    }
+    // Only stakers take up delegator slots, they lose them when fully unbonding
+    let balance = USERS_STATE
+        .may_load(deps.storage, &info.sender)?
+        .map(|u| u.balance)
+        .unwrap_or_default();
+    if balance.is_zero() {
+        return Err(ContractError::InvalidDelegation {});
+    }
+
+    remove_delegation(deps.storage, &info.sender)?;
+    let delegators = DELEGATORS
+        .prefix(&to_addr)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .take(MAX_DELEGATORS)
+        .count();
+    if delegators >= MAX_DELEGATORS {
+        return Err(ContractError::TooManyDelegators {});
+    }
+    DELEGATIONS.save(deps.storage, &info.sender, &to_addr)?;
+    DELEGATORS.save(deps.storage, (&to_addr, &info.sender), &true)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "delegate"),
+        ("user", info.sender.as_str()),
+        ("to", to_addr.as_str()),
+    ]))
+}
+
+pub fn undelegate(deps: DepsMut, _env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
+    let to_addr = DELEGATIONS
+        .may_load(deps.storage, &info.sender)?
+        .ok_or(ContractError::InvalidDelegation {})?;
+    remove_delegation(deps.storage, &info.sender)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "undelegate"),
+        ("user", info.sender.as_str()),
+        ("to", to_addr.as_str()),
+    ]))
+}
+
+pub fn migrate_stake(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    new_contract: String,
+) -> Result<Response, ContractError> {
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let sender = deps.api.addr_humanize(&sender_addr)?;
+    let state = update_rewards(deps.storage, &env)?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
+    let new_contract_addr = deps.api.addr_validate(&new_contract)?;
+    if !MIGRATION_CONTRACTS.has(deps.storage, &new_contract_addr) {
+        return Err(ContractError::Unauthorized {});
+    }
+    // Only cw20 stakes can be sent along with a hook message
+    let token = match &state.asset {
+        StakeAsset::Token { address } => address.clone(),
+        StakeAsset::Native { .. } => return Err(ContractError::InvalidAsset {}),
+    };
+
+    let mut user_state = USERS_STATE
+        .may_load(deps.storage, &sender)?
+        .unwrap_or_default();
+    sync_user_state(&state, &mut user_state);
+    let amount = user_state.balance;
+    if amount.is_zero() {
+        return Err(ContractError::NoZeroAmount {});
+    }
+    USERS_STATE.save(
+        deps.storage,
+        &sender,
+        &UserState {
+            balance: Uint128::zero(),
+            lots: vec![],
+            ..user_state.clone()
+        },
+    )?;
+
+    let new_state = STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.total_balance -= amount;
+        Ok(state)
+    })?;
+    snapshot_balances(
+        deps.storage,
+        &env,
+        &sender,
+        Uint128::zero(),
+        new_state.total_balance,
+    )?;
+    let hooks = member_changed_hooks(deps.storage, &sender, amount, Uint128::zero())?;
+    remove_delegation(deps.storage, &sender)?;
+
+    Ok(Response::new()
+        .add_submessages(hooks)
+        .add_messages(vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: new_contract_addr.to_string(),
+                amount,
+                msg: to_binary(&Cw20HookMsg::MigrateBond {
+                    user: sender.to_string(),
+                    last_deposit: user_state.last_deposit,
+                    lots: user_state.lots,
+                })?,
+            })?,
+            funds: vec![],
+        })])
+        .add_attributes(vec![
+            ("action", "migrate"),
+            ("user", sender.to_string().as_str()),
+            ("contract", new_contract_addr.as_str()),
+            ("amount", amount.to_string().as_str()),
+        ]))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(mut deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let previous = get_contract_version(deps.storage)?;
+    if previous.contract != CONTRACT_NAME {
+        return Err(ContractError::MigrationWrongContract {});
+    }
+    let previous_version = parse_version(&previous.version)?;
+    if previous_version > parse_version(CONTRACT_VERSION)? {
+        return Err(ContractError::MigrationDowngrade {});
+    }
+
+    if previous_version < Version::new(1, 1, 0) {
+        migrate_state_v1_0(deps.branch(), &env)?;
+    }
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "migrate"),
+        ("from_version", previous.version.as_str()),
+        ("to_version", CONTRACT_VERSION),
+    ]))
+}
+
+// Roles default to the owner, tiers have to be set with ConfigureTiers and
+// users seeded with MigrateUsers
+fn migrate_state_v1_0(deps: DepsMut, env: &Env) -> StdResult<()> {
+    let legacy = STATE_V1_0.load(deps.storage)?;
+    let now = env.block.time.seconds();
+    let state = State {
+        owner: legacy.owner.clone(),
+        pending_owner: None,
+        pauser: legacy.owner.clone(),
+        configurer: legacy.owner.clone(),
+        treasury: legacy.owner,
+        asset: StakeAsset::Token {
+            address: deps.api.addr_humanize(&legacy.token)?,
+        },
+        paused: legacy.paused,
+        emergency: false,
+        locked_period: legacy.locked_period,
+        unbonding_period: 0,
+        penalty_rate: 5000,
+        penalty_destination: PenaltyDestination::Treasury {},
+        total_balance: legacy.total_balance,
+        tiers: vec![],
+        max_lock_duration: ONE_YEAR,
+        max_boost: Decimal::zero(),
+        reward_index: Decimal::zero(),
+        reward_amount: Uint128::zero(),
+        reward_end: now,
+        reward_last_update: now,
+    };
+    STATE.save(deps.storage, &state)?;
+    TOTAL_BALANCE.save(deps.storage, &state.total_balance, now)
+}
+
+// Seeds the balance history and cw4 weight of users who bonded before 1.1.0,
+// a page at a time after migrating. Their history starts now like the total.
+pub fn migrate_users(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
     let state = STATE.load(deps.storage)?;
+    if state.owner != sender_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let users = USERS_STATE
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, user_state) = item?;
+            Ok((Addr::unchecked(String::from_utf8(key)?), user_state))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    let now = env.block.time.seconds();
+    let mut migrated = 0u64;
+    for (user, user_state) in &users {
+        // Users with a history changed their balance since the migration
+        if user_state.balance.is_zero() || BALANCES.may_load(deps.storage, user)?.is_some() {
+            continue;
+        }
+        BALANCES.save(deps.storage, user, &user_state.balance, now)?;
+        let observation = Observation {
+            time: now,
+            balance: user_state.balance,
+            cumulative: Uint128::zero(),
+        };
+        OBSERVATIONS.save(deps.storage, user, &observation, now)?;
+        if !FROZEN.has(deps.storage, user) {
+            let weight = member_weight(user_state.balance);
+            save_member(deps.storage, user, weight, env.block.height)?;
+        }
+        migrated += 1;
+    }
+    let last_user = users
+        .last()
+        .map(|(user, _)| user.to_string())
+        .unwrap_or_default();
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "migrate_users"),
+        ("migrated", migrated.to_string().as_str()),
+        ("last_user", last_user.as_str()),
+    ]))
+}
+
+// Hooks can fail without reverting the balance change that triggered them
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(_deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    let error = match msg.result {
+        ContractResult::Err(error) => error,
+        ContractResult::Ok(_) => String::new(),
+    };
+    Ok(Response::new().add_attributes(vec![
+        ("action", "member_changed_hook_failed"),
+        ("error", error.as_str()),
+    ]))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::State {} => to_binary(&query_state(deps, env)?),
+        QueryMsg::UserState { user } => to_binary(&query_user_state(deps, env, user)?),
+        QueryMsg::AllUsers { start_after, limit } => {
+            to_binary(&query_all_users(deps, env, start_after, limit)?)
+        }
+        QueryMsg::UserTier { user } => to_binary(&query_user_tier(deps, env, user)?),
+        QueryMsg::UserPower { user, time } => to_binary(&query_user_power(deps, env, user, time)?),
+        QueryMsg::Unbondings { user } => to_binary(&query_unbondings(deps, env, user)?),
+        QueryMsg::MigrationContract { contract } => {
+            to_binary(&query_migration_contract(deps, contract)?)
+        }
+        QueryMsg::Hooks {} => to_binary(&query_hooks(deps)?),
+        QueryMsg::Assets {} => to_binary(&query_assets(deps)?),
+        QueryMsg::UserAssets { user } => to_binary(&query_user_assets(deps, user)?),
+        QueryMsg::BalanceAt { user, time } => to_binary(&query_balance_at(deps, user, time)?),
+        QueryMsg::TotalBalanceAt { time } => to_binary(&query_total_balance_at(deps, time)?),
+        QueryMsg::AverageBalance { user, from, to } => {
+            to_binary(&query_average_balance(deps, user, from, to)?)
+        }
+        QueryMsg::Member { addr, at_height } => to_binary(&query_member(deps, addr, at_height)?),
+        QueryMsg::ListMembers { start_after, limit } => {
+            to_binary(&query_list_members(deps, start_after, limit)?)
+        }
+        QueryMsg::TotalWeight {} => to_binary(&query_total_weight(deps)?),
+        QueryMsg::Frozen { start_after, limit } => {
+            to_binary(&query_frozen(deps, start_after, limit)?)
+        }
+    }
+}
+
+fn query_state(deps: Deps, env: Env) -> StdResult<StateResponse> {
+    let mut state = STATE.load(deps.storage)?;
+    accrue_rewards(&mut state, env.block.time.seconds());
     Ok(StateResponse {
         owner: deps.api.addr_humanize(&state.owner)?.to_string(),
-        token: deps.api.addr_humanize(&state.token)?.to_string(),
+        pending_owner: state
+            .pending_owner
+            .map(|a| deps.api.addr_humanize(&a).map(|a| a.to_string()))
+            .transpose()?,
+        pauser: deps.api.addr_humanize(&state.pauser)?.to_string(),
+        configurer: deps.api.addr_humanize(&state.configurer)?.to_string(),
+        treasury: deps.api.addr_humanize(&state.treasury)?.to_string(),
+        asset: state.asset,
         paused: state.paused,
+        emergency: state.emergency,
         locked_period: state.locked_period,
+        unbonding_period: state.unbonding_period,
+        penalty_rate: state.penalty_rate,
+        penalty_destination: state.penalty_destination,
         total_balance: state.total_balance,
+        tiers: state.tiers,
+        max_lock_duration: state.max_lock_duration,
+        max_boost: state.max_boost,
+        reward_amount: state.reward_amount,
+        reward_end: state.reward_end,
     })
 }
 
-fn query_user_state(deps: Deps, user: String) -> StdResult<UserStateResponse> {
+fn query_user_state(deps: Deps, env: Env, user: String) -> StdResult<UserStateResponse> {
     let user_addr = deps.api.addr_canonicalize(&user)?;
     let user = deps.api.addr_humanize(&user_addr)?;
+    let mut state = STATE.load(deps.storage)?;
+    accrue_rewards(&mut state, env.block.time.seconds());
     let user_state = USERS_STATE
         .may_load(deps.storage, &user)?
         .unwrap_or_default();
-    Ok(UserStateResponse {
+    Ok(user_state_response(&state, user_state, &env))
+}
+
+fn query_all_users(
+    deps: Deps,
+    env: Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<AllUsersResponse> {
+    let mut state = STATE.load(deps.storage)?;
+    accrue_rewards(&mut state, env.block.time.seconds());
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let users = USERS_STATE
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, user_state) = item?;
+            Ok(UserStateEntry {
+                user: String::from_utf8(key)?,
+                state: user_state_response(&state, user_state, &env),
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(AllUsersResponse { users })
+}
+
+fn query_frozen(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<FrozenResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let users = FROZEN
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(String::from_utf8)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(FrozenResponse { users })
+}
+
+fn user_state_response(state: &State, mut user_state: UserState, env: &Env) -> UserStateResponse {
+    sync_user_state(state, &mut user_state);
+    UserStateResponse {
         last_deposit: user_state.last_deposit,
         balance: user_state.balance,
+        unlocked_balance: unlocked_balance(&user_state.lots, env.block.time.seconds()),
+        lots: user_state.lots,
+        pending_rewards: user_state.pending_rewards,
+    }
+}
+
+fn query_user_tier(deps: Deps, env: Env, user: String) -> StdResult<UserTierResponse> {
+    let user_addr = deps.api.addr_canonicalize(&user)?;
+    let user = deps.api.addr_humanize(&user_addr)?;
+    let state = STATE.load(deps.storage)?;
+    let power = user_power_response(deps, &state, &user, env.block.time.seconds())?;
+    let (tier, multiplier, next_tier_amount) = user_tier(&state.tiers, power.power);
+    Ok(UserTierResponse {
+        balance: power.balance,
+        power: power.power,
+        own_power: power.own_power,
+        delegated_power: power.delegated_power,
+        delegated_to: power.delegated_to,
+        tier,
+        multiplier,
+        next_tier_amount,
+    })
+}
+
+fn query_unbondings(deps: Deps, env: Env, user: String) -> StdResult<UnbondingsResponse> {
+    let user_addr = deps.api.addr_canonicalize(&user)?;
+    let user = deps.api.addr_humanize(&user_addr)?;
+    let unbondings = UNBONDINGS
+        .may_load(deps.storage, &user)?
+        .unwrap_or_default();
+    let claimable = unbondings
+        .iter()
+        .filter(|u| u.release_time <= env.block.time.seconds())
+        .map(|u| u.amount)
+        .sum();
+    Ok(UnbondingsResponse {
+        unbondings,
+        claimable,
+    })
+}
+
+fn query_user_power(
+    deps: Deps,
+    env: Env,
+    user: String,
+    time: Option<u64>,
+) -> StdResult<UserPowerResponse> {
+    let user_addr = deps.api.addr_canonicalize(&user)?;
+    let user = deps.api.addr_humanize(&user_addr)?;
+    let state = STATE.load(deps.storage)?;
+    let time = time.unwrap_or_else(|| env.block.time.seconds());
+    user_power_response(deps, &state, &user, time)
+}
+
+// Delegated power is not passed on again, users delegating out only lose their own power
+fn user_power_response(
+    deps: Deps,
+    state: &State,
+    user: &Addr,
+    time: u64,
+) -> StdResult<UserPowerResponse> {
+    let (balance, own_power) = user_power(deps, state, user, time)?;
+    if FROZEN.has(deps.storage, user) {
+        return Ok(UserPowerResponse {
+            balance,
+            power: Uint128::zero(),
+            own_power,
+            delegated_power: Uint128::zero(),
+            delegated_to: None,
+        });
+    }
+    let delegated_to = DELEGATIONS.may_load(deps.storage, user)?;
+    let delegated_power = DELEGATORS
+        .prefix(user)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .take(MAX_DELEGATORS)
+        .map(|key| {
+            let delegator = Addr::unchecked(String::from_utf8(key)?);
+            Ok(user_power(deps, state, &delegator, time)?.1)
+        })
+        .sum::<StdResult<Uint128>>()?;
+    let power = match delegated_to {
+        Some(_) => delegated_power,
+        None => own_power + delegated_power,
+    };
+    Ok(UserPowerResponse {
+        balance,
+        power,
+        own_power,
+        delegated_power,
+        delegated_to: delegated_to.map(|a| a.to_string()),
+    })
+}
+
+fn query_assets(deps: Deps) -> StdResult<AssetsResponse> {
+    let assets = ASSETS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (key, weight) = item?;
+            Ok(AssetWeight {
+                asset: String::from_utf8(key)?,
+                weight,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(AssetsResponse { assets })
+}
+
+fn query_user_assets(deps: Deps, user: String) -> StdResult<UserAssetsResponse> {
+    let user = deps.api.addr_validate(&user)?;
+    Ok(UserAssetsResponse {
+        assets: user_assets(deps, &user)?,
+    })
+}
+
+fn query_migration_contract(deps: Deps, contract: String) -> StdResult<MigrationContractResponse> {
+    let contract_addr = deps.api.addr_validate(&contract)?;
+    Ok(MigrationContractResponse {
+        allowed: MIGRATION_CONTRACTS.has(deps.storage, &contract_addr),
+    })
+}
+
+fn query_hooks(deps: Deps) -> StdResult<HooksResponse> {
+    let hooks = HOOKS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .map(String::from_utf8)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(HooksResponse { hooks })
+}
+
+// Balance at the end of the block(s) with timestamp `time`
+fn query_balance_at(deps: Deps, user: String, time: u64) -> StdResult<BalanceResponse> {
+    let user_addr = deps.api.addr_canonicalize(&user)?;
+    let user = deps.api.addr_humanize(&user_addr)?;
+    let balance = BALANCES
+        .may_load_at_height(deps.storage, &user, time + 1)?
+        .unwrap_or_default();
+    Ok(BalanceResponse { balance })
+}
+
+fn query_total_balance_at(deps: Deps, time: u64) -> StdResult<BalanceResponse> {
+    let balance = TOTAL_BALANCE
+        .may_load_at_height(deps.storage, time + 1)?
+        .unwrap_or_default();
+    Ok(BalanceResponse { balance })
+}
+
+// Time-weighted average of the balance between `from` and `to`
+fn query_average_balance(
+    deps: Deps,
+    user: String,
+    from: u64,
+    to: u64,
+) -> StdResult<BalanceResponse> {
+    if to <= from {
+        return Err(StdError::generic_err("from must be before to"));
+    }
+    let user_addr = deps.api.addr_canonicalize(&user)?;
+    let user = deps.api.addr_humanize(&user_addr)?;
+    let cumulative = cumulative_balance_at(deps.storage, &user, to)?
+        - cumulative_balance_at(deps.storage, &user, from)?;
+    Ok(BalanceResponse {
+        balance: cumulative.multiply_ratio(1u64, to - from),
+    })
+}
+
+fn query_member(deps: Deps, addr: String, at_height: Option<u64>) -> StdResult<MemberResponse> {
+    let addr = deps.api.addr_validate(&addr)?;
+    let weight = match at_height {
+        Some(height) => MEMBERS.may_load_at_height(deps.storage, &addr, height)?,
+        None => MEMBERS.may_load(deps.storage, &addr)?,
+    };
+    Ok(MemberResponse { weight })
+}
+
+fn query_list_members(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<MemberListResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let members = MEMBERS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, weight) = item?;
+            Ok(Member {
+                addr: String::from_utf8(key)?,
+                weight,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(MemberListResponse { members })
+}
+
+fn query_total_weight(deps: Deps) -> StdResult<TotalWeightResponse> {
+    let total = TOTAL_WEIGHT.may_load(deps.storage)?.unwrap_or_default();
+    Ok(TotalWeightResponse {
+        weight: member_weight(total).unwrap_or_default(),
     })
 }
 
 // HELPERS
 // -----------------------------------------------------
 
+const BPS: u64 = 10000;
+const ONE_YEAR: u64 = 365 * 24 * 60 * 60;
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+// Bounds the power queries, which add up every delegator
+const MAX_DELEGATORS: usize = 30;
+const HOOK_REPLY_ID: u64 = 1;
+const HOOK_GAS_LIMIT: u64 = 500000;
+
+fn unlocked_balance(lots: &[Lot], now: u64) -> Uint128 {
+    lots.iter()
+        .filter(|l| l.unlock_time <= now)
+        .map(|l| l.amount)
+        .sum()
+}
+
+// Inserts a lot keeping them sorted, merging lots that unlock at the same time
+fn add_lot(lots: &mut Vec<Lot>, lot: Lot) {
+    match lots.binary_search_by_key(&lot.unlock_time, |l| l.unlock_time) {
+        Ok(i) => lots[i].amount += lot.amount,
+        Err(i) => lots.insert(i, lot),
+    }
+}
+
+// Removes `amount` from the earliest (or latest) unlocking lots
+fn take_lots(lots: &mut Vec<Lot>, amount: Uint128, latest_first: bool) {
+    let mut remaining = amount;
+    let mut take = |lot: &mut Lot| {
+        let taken = lot.amount.min(remaining);
+        lot.amount -= taken;
+        remaining -= taken;
+    };
+    if latest_first {
+        lots.iter_mut().rev().for_each(&mut take);
+    } else {
+        lots.iter_mut().for_each(&mut take);
+    }
+    lots.retain(|l| !l.amount.is_zero());
+}
+
+// Streams the funded rewards linearly until reward_end. While nothing is
+// bonded the stream is held back for later bonders.
+fn accrue_rewards(state: &mut State, now: u64) {
+    let until = now.min(state.reward_end);
+    if until <= state.reward_last_update || state.total_balance.is_zero() {
+        state.reward_last_update = state.reward_last_update.max(until);
+        return;
+    }
+    let streamed = state.reward_amount.multiply_ratio(
+        until - state.reward_last_update,
+        state.reward_end - state.reward_last_update,
+    );
+    state.reward_index = state.reward_index + Decimal::from_ratio(streamed, state.total_balance);
+    state.reward_amount -= streamed;
+    state.reward_last_update = until;
+}
+
+fn update_rewards(storage: &mut dyn Storage, env: &Env) -> StdResult<State> {
+    let mut state = STATE.load(storage)?;
+    accrue_rewards(&mut state, env.block.time.seconds());
+    STATE.save(storage, &state)?;
+    Ok(state)
+}
+
+// Settles rewards and gives balances bonded before 1.1.0 a lot
+fn sync_user_state(state: &State, user_state: &mut UserState) {
+    let lots_total: Uint128 = user_state.lots.iter().map(|l| l.amount).sum();
+    if user_state.balance > lots_total {
+        let lot = Lot {
+            amount: user_state.balance - lots_total,
+            unlock_time: user_state.last_deposit + state.locked_period,
+        };
+        add_lot(&mut user_state.lots, lot);
+    }
+    settle_rewards(state, user_state);
+}
+
+fn settle_rewards(state: &State, user_state: &mut UserState) {
+    user_state.pending_rewards +=
+        user_state.balance * (state.reward_index - user_state.reward_index);
+    user_state.reward_index = state.reward_index;
+}
+
+fn snapshot_balances(
+    storage: &mut dyn Storage,
+    env: &Env,
+    user: &Addr,
+    balance: Uint128,
+    total_balance: Uint128,
+) -> StdResult<()> {
+    let time = env.block.time.seconds();
+    let cumulative = cumulative_balance_at(storage, user, time)?;
+    OBSERVATIONS.save(
+        storage,
+        user,
+        &Observation {
+            time,
+            balance,
+            cumulative,
+        },
+        time,
+    )?;
+    let weight = match FROZEN.has(storage, user) {
+        true => None,
+        false => member_weight(balance),
+    };
+    save_member(storage, user, weight, env.block.height)?;
+    BALANCES.save(storage, user, &balance, time)?;
+    TOTAL_BALANCE.save(storage, &total_balance, time)
+}
+
+fn save_member(
+    storage: &mut dyn Storage,
+    user: &Addr,
+    weight: Option<u64>,
+    height: u64,
+) -> StdResult<()> {
+    let old = MEMBERS.may_load(storage, user)?.unwrap_or_default();
+    let total = TOTAL_WEIGHT.may_load(storage)?.unwrap_or_default();
+    let total = total - Uint128::from(old) + Uint128::from(weight.unwrap_or_default());
+    TOTAL_WEIGHT.save(storage, &total)?;
+    match weight {
+        Some(weight) => MEMBERS.save(storage, user, &weight, height),
+        None => MEMBERS.remove(storage, user, height),
+    }
+}
+
+fn cumulative_balance_at(storage: &dyn Storage, user: &Addr, time: u64) -> StdResult<Uint128> {
+    let observation = OBSERVATIONS
+        .may_load_at_height(storage, user, time + 1)?
+        .unwrap_or_default();
+    Ok(observation.cumulative
+        + observation
+            .balance
+            .checked_mul(Uint128::from(time - observation.time))?)
+}
+
+fn member_changed_hooks(
+    storage: &dyn Storage,
+    user: &Addr,
+    old: Uint128,
+    new: Uint128,
+) -> StdResult<Vec<SubMsg>> {
+    if old == new || FROZEN.has(storage, user) {
+        return Ok(vec![]);
+    }
+    let msg = to_binary(&MemberChangedExecuteMsg::MemberChangedHook(
+        MemberChangedHookMsg {
+            diffs: vec![MemberDiff {
+                key: user.to_string(),
+                old: member_weight(old),
+                new: member_weight(new),
+            }],
+        },
+    ))?;
+    HOOKS
+        .keys(storage, None, None, Order::Ascending)
+        .map(|key| {
+            let execute = WasmMsg::Execute {
+                contract_addr: String::from_utf8(key)?,
+                msg: msg.clone(),
+                funds: vec![],
+            };
+            Ok(SubMsg::reply_on_error(execute, HOOK_REPLY_ID).with_gas_limit(HOOK_GAS_LIMIT))
+        })
+        .collect()
+}
+
+fn remove_delegation(storage: &mut dyn Storage, user: &Addr) -> StdResult<()> {
+    if let Some(to) = DELEGATIONS.may_load(storage, user)? {
+        DELEGATIONS.remove(storage, user);
+        DELEGATORS.remove(storage, (&to, user));
+    }
+    Ok(())
+}
+
+fn member_weight(balance: Uint128) -> Option<u64> {
+    match balance.u128() {
+        0 => None,
+        b => Some(b.min(u64::MAX as u128) as u64),
+    }
+}
+
+fn validate_tiers(tiers: &[Tier]) -> Result<(), ContractError> {
+    for pair in tiers.windows(2) {
+        if pair[0].min_balance >= pair[1].min_balance {
+            return Err(ContractError::InvalidTiers {});
+        }
+    }
+    Ok(())
+}
+
+fn is_stake_token(state: &State, contract: &Addr) -> bool {
+    matches!(&state.asset, StakeAsset::Token { address } if address == contract)
+}
+
+// Returns the amount of the native stake denom sent, which must be the only funds
+fn native_funds(state: &State, info: &MessageInfo) -> Result<Uint128, ContractError> {
+    let denom = match &state.asset {
+        StakeAsset::Native { denom } => denom,
+        StakeAsset::Token { .. } => return Err(ContractError::InvalidAsset {}),
+    };
+    match info.funds.as_slice() {
+        [coin] if &coin.denom == denom && !coin.amount.is_zero() => Ok(coin.amount),
+        [] => Err(ContractError::NoZeroAmount {}),
+        _ => Err(ContractError::InvalidAsset {}),
+    }
+}
+
+fn transfer_stake(
+    deps: Deps,
+    state: &State,
+    recipient: &Addr,
+    amount: Uint128,
+) -> StdResult<CosmosMsg> {
+    Ok(match &state.asset {
+        StakeAsset::Token { address } => CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: address.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }),
+        StakeAsset::Native { denom } => CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![deduct_tax(
+                deps,
+                Coin {
+                    denom: denom.clone(),
+                    amount,
+                },
+            )?],
+        }),
+    })
+}
+
+fn burn_stake(state: &State, amount: Uint128) -> StdResult<CosmosMsg> {
+    Ok(match &state.asset {
+        StakeAsset::Token { address } => CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: address.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn { amount })?,
+            funds: vec![],
+        }),
+        StakeAsset::Native { denom } => CosmosMsg::Bank(BankMsg::Burn {
+            amount: vec![Coin {
+                denom: denom.clone(),
+                amount,
+            }],
+        }),
+    })
+}
+
+static DECIMAL_FRACTION: Uint128 = Uint128::new(1_000_000_000_000_000_000u128);
+
+pub fn compute_tax(deps: Deps, coin: &Coin) -> StdResult<Uint128> {
+    let terra_querier = TerraQuerier::new(&deps.querier);
+    let tax_rate: Decimal = (terra_querier.query_tax_rate()?).rate;
+    let tax_cap: Uint128 = (terra_querier.query_tax_cap(coin.denom.to_string())?).cap;
+    Ok(std::cmp::min(
+        coin.amount.checked_sub(coin.amount.multiply_ratio(
+            DECIMAL_FRACTION,
+            DECIMAL_FRACTION * tax_rate + DECIMAL_FRACTION,
+        ))?,
+        tax_cap,
+    ))
+}
+
+pub fn deduct_tax(deps: Deps, coin: Coin) -> StdResult<Coin> {
+    let tax_amount = compute_tax(deps, &coin)?;
+    Ok(Coin {
+        denom: coin.denom,
+        amount: coin.amount.checked_sub(tax_amount)?,
+    })
+}
+
+fn parse_version(version: &str) -> StdResult<Version> {
+    version
+        .parse()
+        .map_err(|_| StdError::generic_err(format!("Invalid version: {}", version)))
+}
+
+// Returns the (balance, power) of a user, lots get a boost decaying until they unlock
+fn user_power(deps: Deps, state: &State, user: &Addr, time: u64) -> StdResult<(Uint128, Uint128)> {
+    let mut user_state = USERS_STATE
+        .may_load(deps.storage, user)?
+        .unwrap_or_default();
+    sync_user_state(state, &mut user_state);
+    if FROZEN.has(deps.storage, user) {
+        return Ok((user_state.balance, Uint128::zero()));
+    }
+    let boost: Uint128 = user_state
+        .lots
+        .iter()
+        .map(|l| {
+            let remaining = l.unlock_time.saturating_sub(time);
+            (l.amount * state.max_boost).multiply_ratio(
+                remaining.min(state.max_lock_duration),
+                state.max_lock_duration,
+            )
+        })
+        .sum();
+    let assets: Uint128 = user_assets(deps, user)?
+        .iter()
+        .map(|a| a.amount * a.weight)
+        .sum();
+    Ok((user_state.balance, user_state.balance + boost + assets))
+}
+
+fn validate_lock_duration(state: &State, duration: u64) -> Result<(), ContractError> {
+    if duration < state.locked_period || duration > state.max_lock_duration {
+        return Err(ContractError::InvalidDuration {});
+    }
+    Ok(())
+}
+
+fn user_assets(deps: Deps, user: &Addr) -> StdResult<Vec<UserAsset>> {
+    ASSET_BONDS
+        .prefix(user)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (key, asset_bond) = item?;
+            let asset = Addr::unchecked(String::from_utf8(key)?);
+            Ok(UserAsset {
+                weight: ASSETS.load(deps.storage, &asset)?,
+                asset: asset.to_string(),
+                amount: asset_bond.amount,
+                lots: asset_bond.lots,
+            })
+        })
+        .collect()
+}
+
+// Returns the (tier, multiplier, amount missing for next tier) for a balance
+pub fn user_tier(tiers: &[Tier], balance: Uint128) -> (u64, u64, Uint128) {
+    let index = tiers.iter().filter(|t| balance >= t.min_balance).count();
+    let multiplier = match index {
+        0 => 0,
+        i => tiers[i - 1].multiplier,
+    };
+    let next_tier_amount = tiers
+        .get(index)
+        .map(|t| t.min_balance - balance)
+        .unwrap_or_else(Uint128::zero);
+    (index as u64, multiplier, next_tier_amount)
+}
+
 pub fn balance_of(
     deps: &DepsMut,
     token: &CanonicalAddr,
@@ -9,9 +9,27 @@ use cosmwasm_std::{
 use cw20::{BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
 use std::collections::HashMap;
 use std::marker::PhantomData;
-use terra_cosmwasm::TerraQueryWrapper;
+use terra_cosmwasm::{TaxCapResponse, TaxRateResponse, TerraQuery, TerraQueryWrapper, TerraRoute};
 
 const SEVEN_DAYS: u64 = 7 * 24 * 60 * 60;
+const ONE_DAY: u64 = 24 * 60 * 60;
+
+fn test_tiers() -> Vec<Tier> {
+    vec![
+        Tier {
+            min_balance: Uint128::from(25u128),
+            multiplier: 1,
+        },
+        Tier {
+            min_balance: Uint128::from(75u128),
+            multiplier: 2,
+        },
+        Tier {
+            min_balance: Uint128::from(150u128),
+            multiplier: 4,
+        },
+    ]
+}
 
 pub struct CustomMockQuerier {
     pub base: MockQuerier<TerraQueryWrapper>,
@@ -31,6 +49,27 @@ impl Querier for CustomMockQuerier {
             }
         };
         match &request {
+            QueryRequest::Custom(TerraQueryWrapper { route, query_data }) => {
+                if &TerraRoute::Treasury == route {
+                    match query_data {
+                        TerraQuery::TaxRate {} => {
+                            let res = TaxRateResponse {
+                                rate: Decimal::percent(1),
+                            };
+                            SystemResult::Ok(StdContractResult::from(to_binary(&res)))
+                        }
+                        TerraQuery::TaxCap { denom: _ } => {
+                            let res = TaxCapResponse {
+                                cap: Uint128::from(1000000u128),
+                            };
+                            SystemResult::Ok(StdContractResult::from(to_binary(&res)))
+                        }
+                        _ => panic!("DO NOT ENTER HERE"),
+                    }
+                } else {
+                    panic!("DO NOT ENTER HERE")
+                }
+            }
             QueryRequest::Wasm(WasmQuery::Smart {
                 contract_addr,
                 msg: bin_msg,
@@ -107,8 +146,12 @@ fn test_setup() -> OwnedDeps<MockStorage, MockApi, CustomMockQuerier> {
         .balances
         .insert("token0000".to_string(), token_balances);
     let msg = InstantiateMsg {
-        token: "token0000".to_string(),
+        asset: StakeAsset::Token {
+            address: Addr::unchecked("token0000"),
+        },
         locked_period: 604800,
+        unbonding_period: ONE_DAY,
+        tiers: test_tiers(),
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -119,8 +162,12 @@ fn test_setup() -> OwnedDeps<MockStorage, MockApi, CustomMockQuerier> {
 fn test_instantiate() {
     let mut deps = mock_dependencies(&[]);
     let msg = InstantiateMsg {
-        token: "token0000".to_string(),
+        asset: StakeAsset::Token {
+            address: Addr::unchecked("token0000"),
+        },
         locked_period: SEVEN_DAYS,
+        unbonding_period: ONE_DAY,
+        tiers: test_tiers(),
     };
     let info = mock_info("addr0000", &[]);
     let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -129,18 +176,28 @@ fn test_instantiate() {
     let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
     let value: StateResponse = from_binary(&res).unwrap();
     assert_eq!("addr0000", value.owner.to_string());
-    assert_eq!("token0000", value.token.to_string());
+    assert_eq!(
+        StakeAsset::Token {
+            address: Addr::unchecked("token0000")
+        },
+        value.asset
+    );
     assert_eq!(false, value.paused);
     assert_eq!(604800, value.locked_period);
+    assert_eq!(ONE_DAY, value.unbonding_period);
     assert_eq!("0", value.total_balance.to_string());
+    assert_eq!(test_tiers(), value.tiers);
+    assert_eq!(Decimal::zero(), value.max_boost);
 }
 
 #[test]
 fn test_configure() {
     let mut deps = test_setup();
     let msg = ExecuteMsg::Configure {
-        paused: true,
         locked_period: 123,
+        unbonding_period: 456,
+        penalty_rate: 2500,
+        penalty_destination: PenaltyDestination::Burn {},
     };
     let info = mock_info("addr0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -148,19 +205,34 @@ fn test_configure() {
         res.attributes,
         vec![
             attr("action", "configure"),
-            attr("paused", "true"),
             attr("locked_period", "123"),
+            attr("unbonding_period", "456"),
+            attr("penalty_rate", "2500"),
+            attr("penalty_destination", "burn"),
         ]
     );
 
     let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
     let value: StateResponse = from_binary(&res).unwrap();
-    assert_eq!(true, value.paused);
     assert_eq!(123, value.locked_period);
+    assert_eq!(456, value.unbonding_period);
+    assert_eq!(2500, value.penalty_rate);
+    assert_eq!(PenaltyDestination::Burn {}, value.penalty_destination);
 
     let msg = ExecuteMsg::Configure {
-        paused: true,
         locked_period: 123,
+        unbonding_period: 456,
+        penalty_rate: 10001,
+        penalty_destination: PenaltyDestination::Burn {},
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert_eq!(ContractError::InvalidPenaltyRate {}, err);
+
+    let msg = ExecuteMsg::Configure {
+        locked_period: 123,
+        unbonding_period: 456,
+        penalty_rate: 2500,
+        penalty_destination: PenaltyDestination::Burn {},
     };
     let info = mock_info("addr0001", &[]);
     let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
@@ -168,36 +240,64 @@ fn test_configure() {
 }
 
 #[test]
-fn test_transfer_ownership() {
+fn test_configure_tiers() {
     let mut deps = test_setup();
-    let msg = ExecuteMsg::TransferOwnership {
-        owner: Addr::unchecked("addr0007".to_string()),
+    let tiers = vec![Tier {
+        min_balance: Uint128::from(10u128),
+        multiplier: 3,
+    }];
+    let msg = ExecuteMsg::ConfigureTiers {
+        tiers: tiers.clone(),
     };
     let info = mock_info("addr0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
     assert_eq!(
         res.attributes,
-        vec![
-            attr("action", "transfer_ownership"),
-            attr("owner", "addr0007"),
-        ]
+        vec![attr("action", "configure_tiers"), attr("tiers", "1")]
     );
 
     let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
     let value: StateResponse = from_binary(&res).unwrap();
-    assert_eq!("addr0007", value.owner);
+    assert_eq!(tiers, value.tiers);
 
-    let msg = ExecuteMsg::TransferOwnership {
-        owner: Addr::unchecked("addr0009".to_string()),
+    // Error when thresholds are not increasing
+    let msg = ExecuteMsg::ConfigureTiers {
+        tiers: vec![
+            Tier {
+                min_balance: Uint128::from(10u128),
+                multiplier: 1,
+            },
+            Tier {
+                min_balance: Uint128::from(10u128),
+                multiplier: 2,
+            },
+        ],
     };
-    let info = mock_info("addr0000", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert_eq!(ContractError::InvalidTiers {}, err);
+
+    let msg = ExecuteMsg::ConfigureTiers { tiers };
+    let info = mock_info("addr0001", &[]);
     let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
     assert_eq!(ContractError::Unauthorized {}, err);
 }
 
 #[test]
-fn test_bond() {
+fn test_user_tier() {
     let mut deps = test_setup();
+    let user_tier = |deps: &OwnedDeps<MockStorage, MockApi, CustomMockQuerier>| {
+        let msg = QueryMsg::UserTier {
+            user: "addr0001".to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        from_binary::<UserTierResponse>(&res).unwrap()
+    };
+
+    let value = user_tier(&deps);
+    assert_eq!(0, value.tier);
+    assert_eq!(0, value.multiplier);
+    assert_eq!("25", value.next_tier_amount.to_string());
+
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: "addr0001".to_string(),
         amount: Uint128::from(100u128),
@@ -205,93 +305,429 @@ fn test_bond() {
     });
     let info = mock_info("token0000", &[]);
     execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    let value = user_tier(&deps);
+    assert_eq!("100", value.balance.to_string());
+    assert_eq!(2, value.tier);
+    assert_eq!(2, value.multiplier);
+    // Locks give no boost until it's configured
+    assert_eq!("100", value.power.to_string());
+    assert_eq!("50", value.next_tier_amount.to_string());
 
-    let res = query(
-        deps.as_ref(),
-        mock_env(),
-        QueryMsg::UserState {
-            user: "addr0001".to_string(),
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(50u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    let value = user_tier(&deps);
+    assert_eq!(3, value.tier);
+    assert_eq!(4, value.multiplier);
+    assert_eq!("0", value.next_tier_amount.to_string());
+}
+
+#[test]
+fn test_bond_native() {
+    let mut deps = mock_dependencies(&[]);
+    let msg = InstantiateMsg {
+        asset: StakeAsset::Native {
+            denom: "uluna".to_string(),
         },
-    )
-    .unwrap();
-    let value: UserStateResponse = from_binary(&res).unwrap();
-    assert_eq!("100", value.balance.to_string());
+        locked_period: 0,
+        unbonding_period: 0,
+        tiers: test_tiers(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
-    let value: StateResponse = from_binary(&res).unwrap();
-    assert_eq!("100", value.total_balance.to_string());
+    let msg = ExecuteMsg::BondNative {};
+    let info = mock_info("addr0001", &[Coin::new(100, "uusd")]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::InvalidAsset {}, err);
+    let info = mock_info("addr0001", &[Coin::new(100, "uluna")]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    deps.querier.balances.get_mut("token0000").unwrap().insert(
-        mock_env().contract.address.to_string(),
-        Uint128::from(150u128),
+    // cw20 bonds are refused
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    let info = mock_info("token0000", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+
+    let msg = QueryMsg::UserTier {
+        user: "addr0001".to_string(),
+    };
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let value: UserTierResponse = from_binary(&res).unwrap();
+    assert_eq!(Uint128::from(100u128), value.balance);
+
+    let msg = ExecuteMsg::Unbond {
+        amount: Uint128::from(100u128),
+    };
+    let info = mock_info("addr0001", &[]);
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Claim {}).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![Coin::new(99, "uluna")],
+        }))]
+    );
+}
+
+#[test]
+fn test_bond_for() {
+    let mut deps = test_setup();
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "sale0000".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::BondFor {
+            beneficiary: "addr0001".to_string(),
+        })
+        .unwrap(),
+    });
+    let info = mock_info("addr0002", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let info = mock_info("token0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(attr("user", "addr0001"), res.attributes[1]);
+
+    let msg = QueryMsg::UserState {
+        user: "addr0001".to_string(),
+    };
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let value: UserStateResponse = from_binary(&res).unwrap();
+    assert_eq!(Uint128::from(100u128), value.balance);
+    assert_eq!(
+        mock_env().block.time.seconds() + 604800,
+        value.lots[0].unlock_time
     );
 
+    let msg = QueryMsg::UserState {
+        user: "sale0000".to_string(),
+    };
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let value: UserStateResponse = from_binary(&res).unwrap();
+    assert_eq!(Uint128::zero(), value.balance);
+}
+
+#[test]
+fn test_bond_locked() {
+    let mut deps = test_setup();
+    let msg = ExecuteMsg::ConfigureBoost {
+        max_lock_duration: ONE_YEAR,
+        max_boost: Decimal::one(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    let bond_locked = |duration: u64| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "addr0001".to_string(),
+            amount: Uint128::from(100u128),
+            msg: to_binary(&Cw20HookMsg::BondLocked { duration }).unwrap(),
+        })
+    };
+    let info = mock_info("token0000", &[]);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        bond_locked(ONE_DAY),
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::InvalidDuration {}, err);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        bond_locked(ONE_YEAR + 1),
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::InvalidDuration {}, err);
+    execute(deps.as_mut(), mock_env(), info, bond_locked(ONE_YEAR / 2)).unwrap();
+
+    let msg = QueryMsg::UserPower {
+        user: "addr0001".to_string(),
+        time: None,
+    };
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let value: UserPowerResponse = from_binary(&res).unwrap();
+    assert_eq!(Uint128::from(100u128), value.balance);
+    assert_eq!(Uint128::from(150u128), value.power);
+
+    let msg = QueryMsg::UserTier {
+        user: "addr0001".to_string(),
+    };
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let value: UserTierResponse = from_binary(&res).unwrap();
+    assert_eq!(Uint128::from(150u128), value.power);
+    assert_eq!(3, value.tier);
+
+    // boost is gone once the lot unlocks
+    let msg = QueryMsg::UserPower {
+        user: "addr0001".to_string(),
+        time: Some(mock_env().block.time.seconds() + ONE_YEAR / 2),
+    };
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let value: UserPowerResponse = from_binary(&res).unwrap();
+    assert_eq!(Uint128::from(100u128), value.power);
+
+    let msg = ExecuteMsg::ConfigureBoost {
+        max_lock_duration: ONE_YEAR,
+        max_boost: Decimal::percent(200),
+    };
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = QueryMsg::UserPower {
+        user: "addr0001".to_string(),
+        time: None,
+    };
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let value: UserPowerResponse = from_binary(&res).unwrap();
+    assert_eq!(Uint128::from(200u128), value.power);
+}
+
+#[test]
+fn test_delegate() {
+    let mut deps = test_setup();
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: "addr0001".to_string(),
-        amount: Uint128::from(50u128),
+        amount: Uint128::from(100u128),
         msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
     });
     let info = mock_info("token0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let user_tier = |deps: &OwnedDeps<MockStorage, MockApi, CustomMockQuerier>, user: &str| {
+        let msg = QueryMsg::UserTier {
+            user: user.to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        from_binary::<UserTierResponse>(&res).unwrap()
+    };
+
+    let msg = ExecuteMsg::Delegate {
+        to: "addr0001".to_string(),
+    };
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert_eq!(ContractError::InvalidDelegation {}, err);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::Undelegate {},
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::InvalidDelegation {}, err);
+
+    let msg = ExecuteMsg::Delegate {
+        to: "addr0002".to_string(),
+    };
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
     assert_eq!(
         res.attributes,
         vec![
-            attr("action", "bond"),
+            attr("action", "delegate"),
             attr("user", "addr0001"),
-            attr("tokens", "50"),
-            attr("change", "33"),
-            attr("balance", "133"),
+            attr("to", "addr0002"),
         ]
     );
+
+    let value = user_tier(&deps, "addr0001");
+    assert_eq!(Uint128::from(100u128), value.balance);
+    assert_eq!(Uint128::from(100u128), value.own_power);
+    assert_eq!(Uint128::zero(), value.power);
+    assert_eq!(0, value.tier);
+    assert_eq!(Some("addr0002".to_string()), value.delegated_to);
+    let value = user_tier(&deps, "addr0002");
+    assert_eq!(Uint128::zero(), value.balance);
+    assert_eq!(Uint128::from(100u128), value.delegated_power);
+    assert_eq!(Uint128::from(100u128), value.power);
+    assert_eq!(2, value.tier);
+
+    // moving the delegation takes the power along
+    let msg = ExecuteMsg::Delegate {
+        to: "addr0003".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert_eq!(Uint128::zero(), user_tier(&deps, "addr0002").power);
+    assert_eq!(Uint128::from(100u128), user_tier(&deps, "addr0003").power);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::Undelegate {},
+    )
+    .unwrap();
+    assert_eq!(Uint128::zero(), user_tier(&deps, "addr0003").power);
+    let value = user_tier(&deps, "addr0001");
+    assert_eq!(Uint128::from(100u128), value.power);
+    assert_eq!(None, value.delegated_to);
+
+    // Only stakers can take up delegator slots
+    let msg = ExecuteMsg::Delegate {
+        to: "addr0002".to_string(),
+    };
+    let delegator_info = mock_info("delegator0", &[]);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        delegator_info.clone(),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::InvalidDelegation {}, err);
+    for i in 0..MAX_DELEGATORS {
+        let delegator = format!("delegator{}", i);
+        let bond = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: delegator.clone(),
+            amount: Uint128::from(1u128),
+            msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+        });
+        execute(deps.as_mut(), mock_env(), mock_info("token0000", &[]), bond).unwrap();
+        let info = mock_info(&delegator, &[]);
+        execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+    }
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+    assert_eq!(ContractError::TooManyDelegators {}, err);
+
+    // Unbonding everything frees the slot
+    let unbond = ExecuteMsg::UnbondNow {
+        amount: Uint128::from(1u128),
+    };
+    execute(deps.as_mut(), mock_env(), delegator_info, unbond).unwrap();
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let value = user_tier(&deps, "addr0002");
+    assert_eq!(
+        Uint128::from(100u128 + MAX_DELEGATORS as u128 - 1),
+        value.delegated_power
+    );
 }
 
 #[test]
-fn test_unbond() {
-    // Bond some amount
+fn test_freeze() {
     let mut deps = test_setup();
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+    let bond = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: "addr0001".to_string(),
-        amount: Uint128::from(50u128),
+        amount: Uint128::from(100u128),
         msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
     });
     let info = mock_info("token0000", &[]);
-    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
-    // Mock tokens in contract to equal bonded amount
-    deps.querier.balances.get_mut("token0000").unwrap().insert(
-        mock_env().contract.address.to_string(),
-        Uint128::from(50u128),
-    );
+    execute(deps.as_mut(), mock_env(), info.clone(), bond.clone()).unwrap();
 
-    // Error when not enough balance
-    let msg = ExecuteMsg::Unbond {
-        amount: Uint128::from(51u128),
+    let msg = ExecuteMsg::Freeze {
+        user: "addr0001".to_string(),
     };
-    let info = mock_info("addr0001", &[]);
-    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
-    assert_eq!(ContractError::UnbondBalanceTooLow {}, err);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0001", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
 
-    // Error when before 7 days
-    let msg = ExecuteMsg::Unbond {
-        amount: Uint128::from(25u128),
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), bond.clone()).unwrap_err();
+    assert_eq!(ContractError::Frozen {}, err);
+
+    let msg = QueryMsg::UserTier {
+        user: "addr0001".to_string(),
     };
-    let info = mock_info("addr0001", &[]);
-    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
-    assert_eq!(ContractError::UnbondBefore7Days {}, err);
+    let res = query(deps.as_ref(), mock_env(), msg.clone()).unwrap();
+    let value: UserTierResponse = from_binary(&res).unwrap();
+    assert_eq!(Uint128::from(100u128), value.balance);
+    assert_eq!(Uint128::zero(), value.power);
+    assert_eq!(0, value.tier);
 
-    let msg = ExecuteMsg::Unbond {
-        amount: Uint128::from(20u128),
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Frozen {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let value: FrozenResponse = from_binary(&res).unwrap();
+    assert_eq!(vec!["addr0001".to_string()], value.users);
+
+    let unfreeze = ExecuteMsg::Unfreeze {
+        user: "addr0001".to_string(),
     };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        unfreeze,
+    )
+    .unwrap();
+    execute(deps.as_mut(), mock_env(), info, bond).unwrap();
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let value: UserTierResponse = from_binary(&res).unwrap();
+    assert_eq!(3, value.tier);
+}
+
+#[test]
+fn test_emergency_withdraw() {
+    let mut deps = test_setup();
+    let bond = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    let token_info = mock_info("token0000", &[]);
+    execute(deps.as_mut(), mock_env(), token_info.clone(), bond.clone()).unwrap();
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0009".to_string(),
+        amount: Uint128::from(1000u128),
+        msg: to_binary(&Cw20HookMsg::FundRewards { duration: 100 }).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), token_info.clone(), msg).unwrap();
+
+    let info = mock_info("addr0001", &[]);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::EmergencyWithdraw {},
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::NotEmergency {}, err);
+
+    let msg = ExecuteMsg::Emergency { emergency: true };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let err = execute(deps.as_mut(), mock_env(), token_info.clone(), bond.clone()).unwrap_err();
+    assert_eq!(ContractError::Paused {}, err);
+
+    // still locked, but withdrawn in full with the rewards accrued so far
     let mut env = mock_env();
-    env.block.time = env.block.time.plus_seconds(SEVEN_DAYS);
-    let res = execute(deps.as_mut(), env, info.clone(), msg).unwrap();
+    env.block.time = env.block.time.plus_seconds(50);
+    let res = execute(
+        deps.as_mut(),
+        env,
+        info.clone(),
+        ExecuteMsg::EmergencyWithdraw {},
+    )
+    .unwrap();
     assert_eq!(
         res.attributes,
         vec![
-            attr("action", "unbond"),
+            attr("action", "emergency_withdraw"),
             attr("user", "addr0001"),
-            attr("change", "20"),
-            attr("tokens", "20"),
-            attr("balance", "30"),
+            attr("amount", "600"),
+            attr("rewards", "500"),
         ]
     );
     assert_eq!(
@@ -300,26 +736,1373 @@ fn test_unbond() {
             contract_addr: "token0000".to_string(),
             msg: to_binary(&Cw20ExecuteMsg::Transfer {
                 recipient: "addr0001".to_string(),
-                amount: Uint128::from(20u128),
+                amount: Uint128::from(600u128),
             })
             .unwrap(),
             funds: vec![],
-        }))],
+        }))]
     );
 
-    // Test pausing
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let value: StateResponse = from_binary(&res).unwrap();
+    assert!(value.emergency);
+    assert!(value.paused);
+    assert_eq!(Uint128::zero(), value.total_balance);
+
+    // Resuming doesn't open bonds again while in emergency
+    let owner_info = mock_info("addr0000", &[]);
+    let msg = ExecuteMsg::Pause { paused: false };
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), msg).unwrap();
+    let err = execute(deps.as_mut(), mock_env(), token_info, bond).unwrap_err();
+    assert_eq!(ContractError::Emergency {}, err);
+    let msg = ExecuteMsg::ConfigureAsset {
+        asset: "asset0000".to_string(),
+        weight: Decimal::one(),
+    };
+    execute(deps.as_mut(), mock_env(), owner_info, msg).unwrap();
+    let bond_asset = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    let asset_info = mock_info("asset0000", &[]);
+    let err = execute(deps.as_mut(), mock_env(), asset_info, bond_asset).unwrap_err();
+    assert_eq!(ContractError::Emergency {}, err);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::EmergencyWithdraw {},
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::NoZeroAmount {}, err);
+}
+
+#[test]
+fn test_bond_asset() {
     let mut deps = test_setup();
-    let msg = ExecuteMsg::Configure {
-        paused: true,
-        locked_period: 0,
+    let msg = ExecuteMsg::ConfigureAsset {
+        asset: "lp0000".to_string(),
+        weight: Decimal::percent(200),
     };
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
     let info = mock_info("addr0000", &[]);
-    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    let msg = ExecuteMsg::Unbond {
-        amount: Uint128::from(25u128),
+    let msg = ExecuteMsg::ConfigureAsset {
+        asset: "token0000".to_string(),
+        weight: Decimal::percent(200),
     };
-    let info = mock_info("addr0001", &[]);
+    let info = mock_info("addr0000", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(ContractError::InvalidAsset {}, err);
+
+    for (token, amount) in [("token0000", 10u128), ("lp0000", 20u128)] {
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "addr0001".to_string(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+        });
+        let info = mock_info(token, &[]);
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    let msg = QueryMsg::UserTier {
+        user: "addr0001".to_string(),
+    };
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let value: UserTierResponse = from_binary(&res).unwrap();
+    assert_eq!(Uint128::from(10u128), value.balance);
+    assert_eq!(Uint128::from(50u128), value.power);
+    assert_eq!(1, value.tier);
+
+    // Topping up doesn't relock what was bonded before
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(10u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    let info = mock_info("lp0000", &[]);
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(ONE_DAY);
+    execute(deps.as_mut(), env, info, msg).unwrap();
+
+    let msg = QueryMsg::UserAssets {
+        user: "addr0001".to_string(),
+    };
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let value: UserAssetsResponse = from_binary(&res).unwrap();
+    let start = mock_env().block.time.seconds();
+    assert_eq!(
+        vec![UserAsset {
+            asset: "lp0000".to_string(),
+            amount: Uint128::from(30u128),
+            lots: vec![
+                Lot {
+                    amount: Uint128::from(20u128),
+                    unlock_time: start + SEVEN_DAYS,
+                },
+                Lot {
+                    amount: Uint128::from(10u128),
+                    unlock_time: start + ONE_DAY + SEVEN_DAYS,
+                },
+            ],
+            weight: Decimal::percent(200),
+        }],
+        value.assets
+    );
+
+    let msg = ExecuteMsg::UnbondAsset {
+        asset: "lp0000".to_string(),
+        amount: Uint128::from(20u128),
+    };
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+    assert_eq!(ContractError::UnbondBefore7Days {}, err);
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(SEVEN_DAYS);
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::UnbondAsset {
+            asset: "lp0000".to_string(),
+            amount: Uint128::from(30u128),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::UnbondBefore7Days {}, err);
+    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "lp0000".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0001".to_string(),
+                amount: Uint128::from(20u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))]
+    );
+}
+
+#[test]
+fn test_transfer_ownership() {
+    let mut deps = test_setup();
+    let msg = ExecuteMsg::TransferOwnership {
+        owner: Addr::unchecked("addr0007".to_string()),
+    };
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "transfer_ownership"),
+            attr("pending_owner", "addr0007"),
+        ]
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let value: StateResponse = from_binary(&res).unwrap();
+    assert_eq!("addr0000", value.owner);
+    assert_eq!(Some("addr0007".to_string()), value.pending_owner);
+
+    // Only the proposed owner can accept
+    let msg = ExecuteMsg::AcceptOwnership {};
+    let info = mock_info("addr0009", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+
+    let info = mock_info("addr0007", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "accept_ownership"),
+            attr("owner", "addr0007"),
+        ]
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let value: StateResponse = from_binary(&res).unwrap();
+    assert_eq!("addr0007", value.owner);
+    assert_eq!(None, value.pending_owner);
+
+    let msg = ExecuteMsg::TransferOwnership {
+        owner: Addr::unchecked("addr0009".to_string()),
+    };
+    let info = mock_info("addr0000", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+}
+
+#[test]
+fn test_configure_roles() {
+    let mut deps = test_setup();
+    let msg = ExecuteMsg::ConfigureRoles {
+        pauser: "pauser0000".to_string(),
+        configurer: "configurer0000".to_string(),
+        treasury: "treasury0000".to_string(),
+    };
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let value: StateResponse = from_binary(&res).unwrap();
+    assert_eq!("pauser0000", value.pauser);
+    assert_eq!("configurer0000", value.configurer);
+    assert_eq!("treasury0000", value.treasury);
+
+    // The owner no longer holds the other roles
+    let msg = ExecuteMsg::Pause { paused: true };
+    let info = mock_info("addr0000", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let info = mock_info("pauser0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![attr("action", "pause"), attr("paused", "true")]
+    );
+
+    let msg = ExecuteMsg::ConfigureTiers {
+        tiers: test_tiers(),
+    };
+    let info = mock_info("pauser0000", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let info = mock_info("configurer0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let value: StateResponse = from_binary(&res).unwrap();
+    assert!(value.paused);
+}
+
+#[test]
+fn test_migrate_version() {
+    let mut deps = mock_dependencies(&[]);
+    let start = mock_env().block.time.seconds();
+    cw2::set_contract_version(deps.as_mut().storage, "ts-tiers", "1.0.0").unwrap();
+    let owner = deps.as_ref().api.addr_canonicalize("addr0000").unwrap();
+    let token = deps.as_ref().api.addr_canonicalize("token0000").unwrap();
+    STATE_V1_0
+        .save(
+            deps.as_mut().storage,
+            &StateV1_0 {
+                owner,
+                token,
+                paused: false,
+                locked_period: SEVEN_DAYS,
+                total_balance: Uint128::from(150u128),
+            },
+        )
+        .unwrap();
+    #[derive(Serialize, Deserialize)]
+    struct UserStateV1_0 {
+        last_deposit: u64,
+        balance: Uint128,
+    }
+    let users_v1_0: Map<&Addr, UserStateV1_0> = Map::new("users");
+    users_v1_0
+        .save(
+            deps.as_mut().storage,
+            &Addr::unchecked("addr0001"),
+            &UserStateV1_0 {
+                last_deposit: start - ONE_DAY,
+                balance: Uint128::from(100u128),
+            },
+        )
+        .unwrap();
+    users_v1_0
+        .save(
+            deps.as_mut().storage,
+            &Addr::unchecked("addr0002"),
+            &UserStateV1_0 {
+                last_deposit: start - ONE_DAY,
+                balance: Uint128::from(50u128),
+            },
+        )
+        .unwrap();
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "migrate"),
+            attr("from_version", "1.0.0"),
+            attr("to_version", CONTRACT_VERSION),
+        ]
+    );
+    let version = cw2::get_contract_version(deps.as_ref().storage).unwrap();
+    assert_eq!(CONTRACT_VERSION, version.version);
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let value: StateResponse = from_binary(&res).unwrap();
+    assert_eq!("addr0000", value.owner);
+    assert_eq!("addr0000", value.treasury);
+    assert_eq!(SEVEN_DAYS, value.locked_period);
+    assert_eq!(Uint128::from(150u128), value.total_balance);
+    assert_eq!(Decimal::zero(), value.max_boost);
+
+    // Legacy balances are locked from their last deposit
+    let msg = QueryMsg::UserState {
+        user: "addr0001".to_string(),
+    };
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let value: UserStateResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        vec![Lot {
+            amount: Uint128::from(100u128),
+            unlock_time: start - ONE_DAY + SEVEN_DAYS,
+        }],
+        value.lots
+    );
+    assert_eq!(Uint128::zero(), value.unlocked_balance);
+
+    // Users get their history and cw4 weight a page at a time
+    let total_weight = |deps: &OwnedDeps<_, _, _>| {
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::TotalWeight {}).unwrap();
+        from_binary::<TotalWeightResponse>(&res).unwrap().weight
+    };
+    assert_eq!(0, total_weight(&deps));
+    let msg = ExecuteMsg::MigrateUsers {
+        start_after: None,
+        limit: Some(1),
+    };
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let owner_info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner_info.clone(), msg.clone()).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "migrate_users"),
+            attr("migrated", "1"),
+            attr("last_user", "addr0001"),
+        ]
+    );
+    assert_eq!(100, total_weight(&deps));
+    let res = execute(deps.as_mut(), mock_env(), owner_info.clone(), msg).unwrap();
+    assert_eq!(attr("migrated", "0"), res.attributes[1]);
+    let msg = ExecuteMsg::MigrateUsers {
+        start_after: Some("addr0001".to_string()),
+        limit: Some(1),
+    };
+    execute(deps.as_mut(), mock_env(), owner_info, msg).unwrap();
+    assert_eq!(150, total_weight(&deps));
+
+    let balance_at = |deps: &OwnedDeps<_, _, _>, time: u64| {
+        let msg = QueryMsg::BalanceAt {
+            user: "addr0001".to_string(),
+            time,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        from_binary::<BalanceResponse>(&res).unwrap().balance
+    };
+    assert_eq!(Uint128::zero(), balance_at(&deps, start - 1));
+    assert_eq!(Uint128::from(100u128), balance_at(&deps, start));
+    let total_balance_at = |deps: &OwnedDeps<_, _, _>, time: u64| {
+        let msg = QueryMsg::TotalBalanceAt { time };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        from_binary::<BalanceResponse>(&res).unwrap().balance
+    };
+    assert_eq!(Uint128::zero(), total_balance_at(&deps, start - 1));
+    assert_eq!(Uint128::from(150u128), total_balance_at(&deps, start));
+    let msg = QueryMsg::AverageBalance {
+        user: "addr0001".to_string(),
+        from: start - 2 * ONE_DAY,
+        to: start + 2 * ONE_DAY,
+    };
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let value: BalanceResponse = from_binary(&res).unwrap();
+    assert_eq!(Uint128::from(50u128), value.balance);
+
+    let msg = QueryMsg::Member {
+        addr: "addr0001".to_string(),
+        at_height: None,
+    };
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    assert_eq!(
+        Some(100),
+        from_binary::<MemberResponse>(&res).unwrap().weight
+    );
+
+    let msg = ExecuteMsg::Unbond {
+        amount: Uint128::from(10u128),
+    };
+    let info = mock_info("addr0001", &[]);
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(SEVEN_DAYS);
+    execute(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(Uint128::from(100u128), balance_at(&deps, start));
+    assert_eq!(Uint128::from(90u128), balance_at(&deps, start + SEVEN_DAYS));
+
+    cw2::set_contract_version(deps.as_mut().storage, "ts-tiers", "9.0.0").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert_eq!(ContractError::MigrationDowngrade {}, err);
+
+    cw2::set_contract_version(deps.as_mut().storage, "sale", "1.0.0").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert_eq!(ContractError::MigrationWrongContract {}, err);
+}
+
+#[test]
+fn test_bond() {
+    let mut deps = test_setup();
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    let info = mock_info("token0000", &[]);
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::UserState {
+            user: "addr0001".to_string(),
+        },
+    )
+    .unwrap();
+    let value: UserStateResponse = from_binary(&res).unwrap();
+    assert_eq!("100", value.balance.to_string());
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let value: StateResponse = from_binary(&res).unwrap();
+    assert_eq!("100", value.total_balance.to_string());
+
+    deps.querier.balances.get_mut("token0000").unwrap().insert(
+        mock_env().contract.address.to_string(),
+        Uint128::from(150u128),
+    );
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(50u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    let info = mock_info("token0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "bond"),
+            attr("user", "addr0001"),
+            attr("tokens", "50"),
+            attr("change", "33"),
+            attr("balance", "133"),
+        ]
+    );
+}
+
+#[test]
+fn test_balance_at() {
+    let mut deps = test_setup();
+    let start = mock_env().block.time.seconds();
+    let balance_at = |deps: &OwnedDeps<MockStorage, MockApi, CustomMockQuerier>, time: u64| {
+        let msg = QueryMsg::BalanceAt {
+            user: "addr0001".to_string(),
+            time,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        from_binary::<BalanceResponse>(&res).unwrap().balance
+    };
+    let total_balance_at = |deps: &OwnedDeps<MockStorage, MockApi, CustomMockQuerier>, time| {
+        let msg = QueryMsg::TotalBalanceAt { time };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        from_binary::<BalanceResponse>(&res).unwrap().balance
+    };
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    let info = mock_info("token0000", &[]);
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::UnbondNow {
+        amount: Uint128::from(40u128),
+    };
+    let info = mock_info("addr0001", &[]);
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    execute(deps.as_mut(), env, info.clone(), msg).unwrap();
+
+    assert_eq!(Uint128::zero(), balance_at(&deps, start - 1));
+    assert_eq!(Uint128::from(100u128), balance_at(&deps, start));
+    assert_eq!(Uint128::from(100u128), balance_at(&deps, start + 99));
+    assert_eq!(Uint128::from(60u128), balance_at(&deps, start + 100));
+    assert_eq!(Uint128::zero(), total_balance_at(&deps, start - 1));
+    assert_eq!(Uint128::from(100u128), total_balance_at(&deps, start + 50));
+    assert_eq!(Uint128::from(60u128), total_balance_at(&deps, start + 200));
+}
+
+#[test]
+fn test_member_changed_hooks() {
+    let mut deps = test_setup();
+    let msg = ExecuteMsg::AddHook {
+        addr: "hook0000".to_string(),
+    };
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Hooks {}).unwrap();
+    let value: HooksResponse = from_binary(&res).unwrap();
+    assert_eq!(vec!["hook0000".to_string()], value.hooks);
+
+    let hook = |old: Option<u64>, new: Option<u64>| {
+        let execute = WasmMsg::Execute {
+            contract_addr: "hook0000".to_string(),
+            msg: to_binary(&MemberChangedExecuteMsg::MemberChangedHook(
+                MemberChangedHookMsg {
+                    diffs: vec![MemberDiff {
+                        key: "addr0001".to_string(),
+                        old,
+                        new,
+                    }],
+                },
+            ))
+            .unwrap(),
+            funds: vec![],
+        };
+        SubMsg::reply_on_error(execute, HOOK_REPLY_ID).with_gas_limit(HOOK_GAS_LIMIT)
+    };
+
+    let bond = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    let token_info = mock_info("token0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), token_info.clone(), bond.clone()).unwrap();
+    assert_eq!(vec![hook(None, Some(100))], res.messages);
+
+    let msg = ExecuteMsg::UnbondNow {
+        amount: Uint128::from(40u128),
+    };
+    let info = mock_info("addr0001", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(Some(&hook(Some(100), Some(60))), res.messages.last());
+
+    // A failing hook doesn't revert the unbond
+    let msg = Reply {
+        id: HOOK_REPLY_ID,
+        result: ContractResult::Err("out of gas".to_string()),
+    };
+    let res = reply(deps.as_mut(), mock_env(), msg).unwrap();
+    assert!(res.messages.is_empty());
+
+    let msg = ExecuteMsg::RemoveHook {
+        addr: "hook0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let res = execute(deps.as_mut(), mock_env(), token_info, bond).unwrap();
+    assert!(res.messages.is_empty());
+}
+
+#[test]
+fn test_average_balance() {
+    let mut deps = test_setup();
+    let start = mock_env().block.time.seconds();
+    let average_balance =
+        |deps: &OwnedDeps<MockStorage, MockApi, CustomMockQuerier>, from: u64, to: u64| {
+            let msg = QueryMsg::AverageBalance {
+                user: "addr0001".to_string(),
+                from,
+                to,
+            };
+            let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+            from_binary::<BalanceResponse>(&res).unwrap().balance
+        };
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    let info = mock_info("token0000", &[]);
+    execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    execute(deps.as_mut(), env, info, msg).unwrap();
+
+    assert_eq!(Uint128::zero(), average_balance(&deps, start - 100, start));
+    assert_eq!(
+        Uint128::from(50u128),
+        average_balance(&deps, start - 100, start + 100)
+    );
+    assert_eq!(
+        Uint128::from(100u128),
+        average_balance(&deps, start, start + 100)
+    );
+    assert_eq!(
+        Uint128::from(150u128),
+        average_balance(&deps, start, start + 200)
+    );
+    assert_eq!(
+        Uint128::from(200u128),
+        average_balance(&deps, start + 100, start + 300)
+    );
+
+    let msg = QueryMsg::AverageBalance {
+        user: "addr0001".to_string(),
+        from: start,
+        to: start,
+    };
+    query(deps.as_ref(), mock_env(), msg).unwrap_err();
+}
+
+#[test]
+fn test_cw4_members() {
+    let mut deps = test_setup();
+    let height = mock_env().block.height;
+    let member = |deps: &OwnedDeps<MockStorage, MockApi, CustomMockQuerier>,
+                  at_height: Option<u64>| {
+        let msg = QueryMsg::Member {
+            addr: "addr0001".to_string(),
+            at_height,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        from_binary::<MemberResponse>(&res).unwrap().weight
+    };
+
+    for (user, amount) in [("addr0002", 30u128), ("addr0001", 100u128)] {
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: user.to_string(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+        });
+        let info = mock_info("token0000", &[]);
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    let msg = QueryMsg::ListMembers {
+        start_after: None,
+        limit: None,
+    };
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let value: MemberListResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        vec![
+            Member {
+                addr: "addr0001".to_string(),
+                weight: 100,
+            },
+            Member {
+                addr: "addr0002".to_string(),
+                weight: 30,
+            },
+        ],
+        value.members
+    );
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::TotalWeight {}).unwrap();
+    let value: TotalWeightResponse = from_binary(&res).unwrap();
+    assert_eq!(130, value.weight);
+
+    let msg = ExecuteMsg::UnbondNow {
+        amount: Uint128::from(100u128),
+    };
+    let info = mock_info("addr0001", &[]);
+    let mut env = mock_env();
+    env.block.height += 5;
+    execute(deps.as_mut(), env, info, msg).unwrap();
+
+    assert_eq!(None, member(&deps, None));
+    assert_eq!(None, member(&deps, Some(height)));
+    assert_eq!(Some(100), member(&deps, Some(height + 1)));
+    assert_eq!(Some(100), member(&deps, Some(height + 5)));
+    assert_eq!(None, member(&deps, Some(height + 6)));
+
+    // Frozen users aren't members
+    let total_weight = |deps: &OwnedDeps<MockStorage, MockApi, CustomMockQuerier>| {
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::TotalWeight {}).unwrap();
+        from_binary::<TotalWeightResponse>(&res).unwrap().weight
+    };
+    let freeze = ExecuteMsg::Freeze {
+        user: "addr0002".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    let add_hook = ExecuteMsg::AddHook {
+        addr: "hook0000".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), add_hook).unwrap();
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), freeze.clone()).unwrap();
+    assert_eq!(1, res.messages.len());
+    // Freezing again changes nothing
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), freeze).unwrap();
+    assert!(res.messages.is_empty());
+    let msg = QueryMsg::Member {
+        addr: "addr0002".to_string(),
+        at_height: None,
+    };
+    let res = query(deps.as_ref(), mock_env(), msg.clone()).unwrap();
+    assert_eq!(None, from_binary::<MemberResponse>(&res).unwrap().weight);
+    assert_eq!(0, total_weight(&deps));
+
+    let unfreeze = ExecuteMsg::Unfreeze {
+        user: "addr0002".to_string(),
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), unfreeze.clone()).unwrap();
+    assert_eq!(1, res.messages.len());
+    let res = execute(deps.as_mut(), mock_env(), info, unfreeze).unwrap();
+    assert!(res.messages.is_empty());
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    assert_eq!(
+        Some(30),
+        from_binary::<MemberResponse>(&res).unwrap().weight
+    );
+    assert_eq!(30, total_weight(&deps));
+}
+
+#[test]
+fn test_all_users() {
+    let mut deps = test_setup();
+    for (user, amount) in [("addr0002", 30u128), ("addr0001", 100u128)] {
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: user.to_string(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+        });
+        let info = mock_info("token0000", &[]);
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    let msg = QueryMsg::AllUsers {
+        start_after: None,
+        limit: Some(1),
+    };
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let value: AllUsersResponse = from_binary(&res).unwrap();
+    assert_eq!(1, value.users.len());
+    assert_eq!("addr0001", value.users[0].user);
+    assert_eq!(Uint128::from(100u128), value.users[0].state.balance);
+
+    let msg = QueryMsg::AllUsers {
+        start_after: Some("addr0001".to_string()),
+        limit: None,
+    };
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let value: AllUsersResponse = from_binary(&res).unwrap();
+    assert_eq!(1, value.users.len());
+    assert_eq!("addr0002", value.users[0].user);
+    assert_eq!(Uint128::from(30u128), value.users[0].state.balance);
+}
+
+#[test]
+fn test_migrate() {
+    let mut deps = test_setup();
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    let info = mock_info("token0000", &[]);
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // Error when the successor is not whitelisted
+    let msg = ExecuteMsg::Migrate {
+        new_contract: "tiers0002".to_string(),
+    };
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+
+    let msg = ExecuteMsg::ConfigureMigration {
+        contract: "tiers0002".to_string(),
+        allowed: true,
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::Migrate {
+        new_contract: "tiers0002".to_string(),
+    };
+    let info = mock_info("addr0001", &[]);
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(60);
+    let res = execute(deps.as_mut(), env, info.clone(), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "migrate"),
+            attr("user", "addr0001"),
+            attr("contract", "tiers0002"),
+            attr("amount", "100"),
+        ]
+    );
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "token0000".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: "tiers0002".to_string(),
+                amount: Uint128::from(100u128),
+                msg: to_binary(&Cw20HookMsg::MigrateBond {
+                    user: "addr0001".to_string(),
+                    last_deposit: mock_env().block.time.seconds(),
+                    lots: vec![Lot {
+                        amount: Uint128::from(100u128),
+                        unlock_time: mock_env().block.time.seconds() + SEVEN_DAYS,
+                    }],
+                })
+                .unwrap(),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))],
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let value: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(Uint128::zero(), value.total_balance);
+
+    // Receiving side only accepts stakes from whitelisted contracts
+    let mut deps = test_setup();
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "tiers0001".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::MigrateBond {
+            user: "addr0001".to_string(),
+            last_deposit: 123,
+            lots: vec![Lot {
+                amount: Uint128::from(100u128),
+                unlock_time: 123 + SEVEN_DAYS,
+            }],
+        })
+        .unwrap(),
+    });
+    let info = mock_info("token0000", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+
+    let configure_msg = ExecuteMsg::ConfigureMigration {
+        contract: "tiers0001".to_string(),
+        allowed: true,
+    };
+    let owner_info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), owner_info, configure_msg).unwrap();
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::UserState {
+            user: "addr0001".to_string(),
+        },
+    )
+    .unwrap();
+    let value: UserStateResponse = from_binary(&res).unwrap();
+    assert_eq!(123, value.last_deposit);
+    assert_eq!("100", value.balance.to_string());
+    assert_eq!("100", value.unlocked_balance.to_string());
+}
+
+#[test]
+fn test_unbond() {
+    // Bond some amount
+    let mut deps = test_setup();
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(50u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    let info = mock_info("token0000", &[]);
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    // Mock tokens in contract to equal bonded amount
+    deps.querier.balances.get_mut("token0000").unwrap().insert(
+        mock_env().contract.address.to_string(),
+        Uint128::from(50u128),
+    );
+
+    // Error when not enough balance
+    let msg = ExecuteMsg::Unbond {
+        amount: Uint128::from(51u128),
+    };
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert_eq!(ContractError::UnbondBalanceTooLow {}, err);
+
+    // Error when before 7 days
+    let msg = ExecuteMsg::Unbond {
+        amount: Uint128::from(25u128),
+    };
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert_eq!(ContractError::UnbondBefore7Days {}, err);
+
+    let msg = ExecuteMsg::Unbond {
+        amount: Uint128::from(20u128),
+    };
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(SEVEN_DAYS);
+    let release_time = env.block.time.seconds() + ONE_DAY;
+    let res = execute(deps.as_mut(), env, info.clone(), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "unbond"),
+            attr("user", "addr0001"),
+            attr("change", "20"),
+            attr("balance", "30"),
+            attr("locked_balance", "0"),
+            attr("release_time", release_time.to_string()),
+        ]
+    );
+    assert_eq!(0, res.messages.len());
+
+    // Unbonded tokens stop counting right away
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::UserTier {
+            user: "addr0001".to_string(),
+        },
+    )
+    .unwrap();
+    let value: UserTierResponse = from_binary(&res).unwrap();
+    assert_eq!("30", value.balance.to_string());
+    assert_eq!(1, value.tier);
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Unbondings {
+            user: "addr0001".to_string(),
+        },
+    )
+    .unwrap();
+    let value: UnbondingsResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        vec![Unbonding {
+            amount: Uint128::from(20u128),
+            release_time,
+        }],
+        value.unbondings
+    );
+    assert_eq!(Uint128::zero(), value.claimable);
+
+    // Test pausing
+    let mut deps = test_setup();
+    let msg = ExecuteMsg::Pause { paused: true };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::Unbond {
+        amount: Uint128::from(25u128),
+    };
+    let info = mock_info("addr0001", &[]);
     let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
     assert_eq!(ContractError::Paused {}, err);
 }
+
+#[test]
+fn test_unbond_lots() {
+    let mut deps = test_setup();
+    let bond = |deps: &mut OwnedDeps<_, _, _>, amount: u128, days: u64| {
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "addr0001".to_string(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+        });
+        let info = mock_info("token0000", &[]);
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(days * ONE_DAY);
+        execute(deps.as_mut(), env, info, msg).unwrap();
+    };
+    bond(&mut deps, 100, 0);
+    bond(&mut deps, 10, 3);
+    bond(&mut deps, 5, 5);
+
+    // A top-up doesn't re-lock the earlier lots
+    let info = mock_info("addr0001", &[]);
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(SEVEN_DAYS);
+    let msg = ExecuteMsg::Unbond {
+        amount: Uint128::from(101u128),
+    };
+    let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+    assert_eq!(ContractError::UnbondBefore7Days {}, err);
+
+    let msg = ExecuteMsg::Unbond {
+        amount: Uint128::from(60u128),
+    };
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    assert_eq!(attr("balance", "55"), res.attributes[3]);
+    assert_eq!(attr("locked_balance", "15"), res.attributes[4]);
+
+    // Early unbonds are taken from the latest unlocking lots
+    let msg = ExecuteMsg::UnbondNow {
+        amount: Uint128::from(7u128),
+    };
+    execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    let msg = QueryMsg::UserState {
+        user: "addr0001".to_string(),
+    };
+    let res = query(deps.as_ref(), env.clone(), msg).unwrap();
+    let value: UserStateResponse = from_binary(&res).unwrap();
+    let start = mock_env().block.time.seconds();
+    assert_eq!("48", value.balance.to_string());
+    assert_eq!("40", value.unlocked_balance.to_string());
+    assert_eq!(
+        vec![
+            Lot {
+                amount: Uint128::from(40u128),
+                unlock_time: start + SEVEN_DAYS,
+            },
+            Lot {
+                amount: Uint128::from(8u128),
+                unlock_time: start + 3 * ONE_DAY + SEVEN_DAYS,
+            },
+        ],
+        value.lots
+    );
+}
+
+#[test]
+fn test_unbond_now() {
+    let mut deps = test_setup();
+    let bond = |deps: &mut OwnedDeps<_, _, _>, user: &str, amount: u128| {
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: user.to_string(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+        });
+        let info = mock_info("token0000", &[]);
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    };
+    let configure = |deps: &mut OwnedDeps<_, _, _>, penalty_destination| {
+        let msg = ExecuteMsg::Configure {
+            locked_period: SEVEN_DAYS,
+            unbonding_period: ONE_DAY,
+            penalty_rate: 2000,
+            penalty_destination,
+        };
+        let info = mock_info("addr0000", &[]);
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    };
+    let transfer = |recipient: &str, amount: u128| {
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "token0000".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount: Uint128::from(amount),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))
+    };
+    bond(&mut deps, "addr0001", 100);
+    bond(&mut deps, "addr0002", 300);
+    let info = mock_info("addr0001", &[]);
+    let msg = ExecuteMsg::UnbondNow {
+        amount: Uint128::from(10u128),
+    };
+
+    // Defaults to 50% sent to the treasury, which starts as the owner
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "unbond_now"),
+            attr("user", "addr0001"),
+            attr("change", "10"),
+            attr("penalty", "5"),
+            attr("balance", "90"),
+        ]
+    );
+    assert_eq!(
+        res.messages,
+        vec![transfer("addr0001", 5), transfer("addr0000", 5)]
+    );
+
+    let roles = ExecuteMsg::ConfigureRoles {
+        pauser: "addr0000".to_string(),
+        configurer: "addr0000".to_string(),
+        treasury: "treasury0000".to_string(),
+    };
+    let owner_info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), owner_info, roles).unwrap();
+    configure(&mut deps, PenaltyDestination::Treasury {});
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![transfer("addr0001", 8), transfer("treasury0000", 2)]
+    );
+
+    configure(&mut deps, PenaltyDestination::Burn {});
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "token0000".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn {
+                amount: Uint128::from(2u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))
+    );
+
+    // Penalty goes to the 300 tokens of the other bonder
+    configure(&mut deps, PenaltyDestination::Redistribute {});
+    let msg = ExecuteMsg::UnbondNow {
+        amount: Uint128::from(50u128),
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert_eq!(res.messages, vec![transfer("addr0001", 40)]);
+
+    let user_state = |deps: &OwnedDeps<_, _, _>, user: &str| {
+        let msg = QueryMsg::UserState {
+            user: user.to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        from_binary::<UserStateResponse>(&res).unwrap()
+    };
+    assert_eq!(
+        Uint128::zero(),
+        user_state(&deps, "addr0001").pending_rewards
+    );
+    assert_eq!(
+        Uint128::from(9u128),
+        user_state(&deps, "addr0002").pending_rewards
+    );
+
+    let info = mock_info("addr0002", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::ClaimRewards {},
+    )
+    .unwrap();
+    assert_eq!(res.messages, vec![transfer("addr0002", 9)]);
+    assert_eq!(
+        Uint128::zero(),
+        user_state(&deps, "addr0002").pending_rewards
+    );
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::ClaimRewards {},
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::NoZeroAmount {}, err);
+
+    // A partial unbonder doesn't get back any of their own penalty
+    let msg = ExecuteMsg::UnbondNow {
+        amount: Uint128::from(200u128),
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert_eq!(res.messages, vec![transfer("addr0002", 160)]);
+    assert_eq!(
+        Uint128::zero(),
+        user_state(&deps, "addr0002").pending_rewards
+    );
+    assert_eq!(
+        Uint128::from(40u128),
+        user_state(&deps, "addr0001").pending_rewards
+    );
+
+    // With nobody else bonded the penalty goes to the treasury, only the
+    // rewards from addr0001's penalty are left
+    let msg = ExecuteMsg::UnbondNow {
+        amount: Uint128::from(20u128),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), msg).unwrap();
+    let msg = ExecuteMsg::UnbondNow {
+        amount: Uint128::from(50u128),
+    };
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![transfer("addr0002", 40), transfer("treasury0000", 10)]
+    );
+    assert_eq!(
+        Uint128::from(4u128),
+        user_state(&deps, "addr0002").pending_rewards
+    );
+}
+
+#[test]
+fn test_fund_rewards() {
+    let mut deps = test_setup();
+    let info = mock_info("token0000", &[]);
+    let bond = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), info.clone(), bond).unwrap();
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0009".to_string(),
+        amount: Uint128::from(1000u128),
+        msg: to_binary(&Cw20HookMsg::FundRewards { duration: 100 }).unwrap(),
+    });
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "fund_rewards"),
+            attr("funder", "addr0009"),
+            attr("amount", "1000"),
+            attr("duration", "100"),
+            attr("reward_amount", "1000"),
+        ]
+    );
+
+    // addr0002 joins halfway with 3x the balance
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(50);
+    let bond = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0002".to_string(),
+        amount: Uint128::from(300u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    execute(deps.as_mut(), env, info.clone(), bond).unwrap();
+
+    let pending_rewards = |deps: &OwnedDeps<_, _, _>, user: &str, seconds: u64| {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(seconds);
+        let msg = QueryMsg::UserState {
+            user: user.to_string(),
+        };
+        let res = query(deps.as_ref(), env, msg).unwrap();
+        from_binary::<UserStateResponse>(&res)
+            .unwrap()
+            .pending_rewards
+    };
+    assert_eq!(
+        Uint128::from(500u128),
+        pending_rewards(&deps, "addr0001", 50)
+    );
+    assert_eq!(
+        Uint128::from(625u128),
+        pending_rewards(&deps, "addr0001", 100)
+    );
+    assert_eq!(
+        Uint128::from(375u128),
+        pending_rewards(&deps, "addr0002", 100)
+    );
+    assert_eq!(
+        Uint128::from(375u128),
+        pending_rewards(&deps, "addr0002", 500)
+    );
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(200);
+    let info = mock_info("addr0001", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::ClaimRewards {}).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "token0000".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0001".to_string(),
+                amount: Uint128::from(625u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))]
+    );
+
+    // Only the bonded token can be used for rewards
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0009".to_string(),
+        amount: Uint128::from(1000u128),
+        msg: to_binary(&Cw20HookMsg::FundRewards { duration: 100 }).unwrap(),
+    });
+    let info = mock_info("token0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err);
+}
+
+#[test]
+fn test_claim() {
+    let mut deps = test_setup();
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(50u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    let info = mock_info("token0000", &[]);
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let info = mock_info("addr0001", &[]);
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(SEVEN_DAYS);
+    for amount in [10u128, 15u128] {
+        let msg = ExecuteMsg::Unbond {
+            amount: Uint128::from(amount),
+        };
+        execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        env.block.time = env.block.time.plus_seconds(ONE_DAY / 2);
+    }
+
+    // Error when nothing has been released yet
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(SEVEN_DAYS + ONE_DAY - 1);
+    let err = execute(deps.as_mut(), env, info.clone(), ExecuteMsg::Claim {}).unwrap_err();
+    assert_eq!(ContractError::NoZeroAmount {}, err);
+
+    // Only the first request has matured
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(SEVEN_DAYS + ONE_DAY);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Claim {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "claim"),
+            attr("user", "addr0001"),
+            attr("amount", "10"),
+        ]
+    );
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "token0000".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0001".to_string(),
+                amount: Uint128::from(10u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))],
+    );
+
+    env.block.time = env.block.time.plus_seconds(ONE_DAY / 2);
+    let msg = QueryMsg::Unbondings {
+        user: "addr0001".to_string(),
+    };
+    let res = query(deps.as_ref(), env.clone(), msg).unwrap();
+    let value: UnbondingsResponse = from_binary(&res).unwrap();
+    assert_eq!(1, value.unbondings.len());
+    assert_eq!(Uint128::from(15u128), value.claimable);
+
+    let res = execute(deps.as_mut(), env, info.clone(), ExecuteMsg::Claim {}).unwrap();
+    assert_eq!(attr("amount", "15"), res.attributes[2]);
+}